@@ -0,0 +1,111 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use scout::common::{Text, TextBuilder};
+use scout::fuzzy::{search, Matcher};
+
+const POOL_SIZE: usize = 50_000;
+// Matches the engine keeps on a new search
+const LIMIT: usize = 200;
+
+const ASCII_WORDS: &[&str] = &[
+    "src",
+    "lib",
+    "ui",
+    "components",
+    "layout",
+    "tests",
+    "fixtures",
+    "config",
+    "main",
+    "utils",
+    "core",
+    "app",
+    "models",
+    "views",
+    "controllers",
+    "assets",
+    "images",
+    "docs",
+    "README",
+    "index",
+    "Cargo",
+    "node_modules",
+    "vendor",
+    "build",
+    "FuzzyFinder",
+    "person_input",
+];
+
+const UNICODE_WORDS: &[&str] = &[
+    "canción",
+    "niño",
+    "Ångström",
+    "façade",
+    "crème_brûlée",
+    "Überblick",
+    "日本語",
+    "señal",
+];
+
+const EXTENSIONS: &[&str] = &[".rs", ".js", ".md", ".toml", ".json", ".ts", ".png"];
+
+const QUERIES: &[&str] = &["comp", "srcuilay", "READ", "fuzfin", "xyzq"];
+
+// Small pseudo random generator, so every run uses the same corpus
+struct Lcg(u64);
+
+impl Lcg {
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+
+        items[(self.0 >> 33) as usize % items.len()]
+    }
+}
+
+// Paths like the output of `find` in a project, using the given words for directories and files.
+// They are stored in chunks, like the lines of the engine's pool
+fn corpus(words: &[&str]) -> Vec<Text> {
+    let mut rng = Lcg(42);
+    let depths = ["1", "2", "3", "4", "5"];
+    let mut builder = TextBuilder::default();
+
+    for _ in 0..POOL_SIZE {
+        let depth: usize = rng.pick(&depths).parse().unwrap();
+        let mut path = (0..depth)
+            .map(|_| rng.pick(words))
+            .collect::<Vec<_>>()
+            .join("/");
+        path.push_str(rng.pick(EXTENSIONS));
+
+        builder.push(&path);
+    }
+
+    builder.finish()
+}
+
+fn bench_search(c: &mut Criterion) {
+    let mut unicode_words = ASCII_WORDS.to_vec();
+    unicode_words.extend_from_slice(UNICODE_WORDS);
+
+    let corpora = [
+        ("ascii", corpus(ASCII_WORDS)),
+        ("unicode", corpus(&unicode_words)),
+    ];
+
+    for (name, pool) in corpora.iter() {
+        let mut group = c.benchmark_group(format!("search_{}", name));
+
+        for query in QUERIES {
+            group.bench_with_input(BenchmarkId::from_parameter(query), query, |b, query| {
+                b.iter(|| search(&Matcher::from(black_box(*query)), pool, false, &[], LIMIT))
+            });
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_search);
+criterion_main!(benches);
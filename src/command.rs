@@ -0,0 +1,142 @@
+//! Run external commands from key bindings
+//!
+//! Commands run with `sh -c` and can use placeholders that are replaced before running them:
+//!
+//! * `{}`: the selected match
+//! * `{q}`: the current query
+//! * `{+}`: the marked matches, or the selected one if nothing is marked
+//! * `{n}`: the index of the selected match in the list, starting from 0
+//!
+//! Each replacement is quoted, so the command receives it as a single argument.
+
+use crate::common::{self, Result};
+use crate::ptty::{self, PTTY};
+use crate::state::State;
+use async_std::os::unix::io::AsRawFd;
+use async_std::task;
+use std::convert::TryFrom;
+use std::fs::OpenOptions;
+use std::process::{Command, Stdio};
+
+/// Replace the placeholders of the command with the current state
+pub fn expand(template: &str, state: &State) -> String {
+    let selection = state
+        .selection()
+        .map(|text| text.to_string())
+        .unwrap_or_default();
+    let index = state
+        .selection()
+        .map(|_| state.selection_idx().to_string())
+        .unwrap_or_default();
+    let query = state.query();
+
+    // Each value is quoted, so the command gets it as a single argument.
+    // Every marked match is a different argument
+    let (selection, query, index) = (quote(&selection), quote(&query), quote(&index));
+    let marked = if state.marked().is_empty() {
+        selection.clone()
+    } else {
+        state
+            .marked()
+            .iter()
+            .map(|text| quote(text.as_str()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let placeholders = [
+        ("{}", selection.as_str()),
+        ("{+}", marked.as_str()),
+        ("{q}", query.as_str()),
+        ("{n}", index.as_str()),
+    ];
+
+    common::expand(template, &placeholders)
+}
+
+/// Run the command in the terminal and wait until it finishes.
+///
+/// The terminal goes back to canonical mode while the command runs, so it works as in a shell
+pub async fn execute(command: &str) -> Result<()> {
+    log::trace!("executing {:?}", command);
+
+    let tty = ptty::file().await?;
+    // The current (noncanonical) mode is restored when the PTTY is dropped
+    let ptty = PTTY::try_from(tty.as_raw_fd())?;
+    ptty.canonical_mode()?;
+
+    let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let mut command = shell(command);
+    command
+        .stdin(tty.try_clone()?)
+        .stdout(tty.try_clone()?)
+        .stderr(tty);
+
+    let status = task::spawn_blocking(move || command.status()).await?;
+    log::trace!("command finished with {:?}", status);
+
+    Ok(())
+}
+
+/// Run the command in the background, without any input or output
+pub fn execute_silent(command: &str) -> Result<()> {
+    log::trace!("executing {:?} in the background", command);
+
+    let mut child = shell(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Wait for the command somewhere else, so it doesn't become a zombie process
+    task::spawn_blocking(move || child.wait());
+
+    Ok(())
+}
+
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+
+    shell
+}
+
+// Quote the text as a single argument for the shell
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r#"'\''"#))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{Prompt, TextBuilder};
+    use crate::fuzzy::Candidate;
+
+    #[test]
+    fn expand_test() {
+        let mut state = State::new();
+        state.set_search((&Prompt::from(&"it's".to_string())).into());
+
+        assert_eq!(expand("vim {} {n}", &state), "vim '' ''");
+
+        state.set_matches((
+            vec![
+                Candidate::from(&TextBuilder::build("foo.rs")),
+                Candidate::from(&TextBuilder::build("bar baz.rs")),
+            ]
+            .into(),
+            2,
+        ));
+        state.select_down();
+
+        assert_eq!(
+            expand("vim {} # {q} {n} {+} {x} {", &state),
+            r#"vim 'bar baz.rs' # 'it'\''s' '1' 'bar baz.rs' {x} {"#
+        );
+
+        state.toggle_mark();
+        state.select_up();
+        state.toggle_mark();
+
+        assert_eq!(expand("rm {+}", &state), "rm 'bar baz.rs' 'foo.rs'");
+    }
+}
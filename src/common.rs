@@ -1,27 +1,142 @@
 //! Set of common types used through the app
 
+use crate::fuzzy::is_word_separator;
 use async_std::sync::Arc;
 use std::fmt;
-use std::slice::Iter;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::time::Instant;
 use unicode_segmentation::UnicodeSegmentation;
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
+/// Replace the placeholders of the template (like `{q}`) with their values.
+///
+/// Braces that are not part of a placeholder are kept as they are
+pub fn expand(template: &str, placeholders: &[(&str, &str)]) -> String {
+    let mut expanded = String::new();
+    let mut rest = template;
+
+    while let Some(idx) = rest.find('{') {
+        expanded.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        match placeholders
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+        {
+            Some((placeholder, value)) => {
+                expanded.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+
+    expanded
+}
+
+const KILL_RING_SIZE: usize = 10;
+const HISTORY_SIZE: usize = 100;
+
 /// The Prompt represents the current query, the cursor position in that query and when it was
 /// updated.
 ///
 /// When the query in the prompt changes the timestamp is updated to reflect that is a fresh query.
 /// This is then used to print to the UI only latest changes.
+///
+/// The prompt supports a set of readline-like editing actions. Text deleted by the kill actions
+/// (like `kill_to_end` or `delete_word_backward`) is kept in a kill ring and can be inserted again
+/// with `yank`. Words are delimited by the same separators used by the fuzzy matching algorithm.
+///
+/// Every edit can be undone (and redone) restoring both the query and the cursor position.
+/// Consecutive characters typed are undone at once.
+///
+/// With the vi editing mode the prompt can also be in normal mode, where keys are commands
+/// instead of characters for the query.
 #[derive(Debug, Clone)]
 pub struct Prompt {
     query: Vec<char>,
     cursor: usize,
     timestamp: Instant,
+    kill_ring: Vec<String>,
+    undo_stack: Vec<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    typing: bool,
+    mode: PromptMode,
+}
+
+/// Whether the keys typed in the prompt are inserted in the query or used as vi commands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptMode {
+    Insert,
+    Normal,
+}
+
+impl Default for PromptMode {
+    fn default() -> Self {
+        PromptMode::Insert
+    }
+}
+
+/// What the rest of the program needs from the prompt to search and print it: the query, the
+/// cursor position, the mode and when the query was updated. The editing history stays with the
+/// prompt
+#[derive(Debug, Clone)]
+pub struct Search {
+    query: String,
+    cursor_until_end: usize,
+    mode: PromptMode,
+    timestamp: Instant,
+}
+
+impl Search {
+    pub fn as_string(&self) -> String {
+        self.query.clone()
+    }
+
+    pub fn cursor_until_end(&self) -> usize {
+        self.cursor_until_end
+    }
+
+    pub fn mode(&self) -> PromptMode {
+        self.mode
+    }
+
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+}
+
+impl From<&Prompt> for Search {
+    fn from(prompt: &Prompt) -> Self {
+        Self {
+            query: prompt.as_string(),
+            cursor_until_end: prompt.cursor_until_end(),
+            mode: prompt.mode(),
+            timestamp: prompt.timestamp(),
+        }
+    }
+}
+
+/// Query and cursor position before an edit
+#[derive(Debug, Clone)]
+struct Snapshot {
+    query: Vec<char>,
+    cursor: usize,
 }
 
 impl Prompt {
     pub fn add(&mut self, ch: char) {
+        if !self.typing {
+            self.save();
+            self.typing = true;
+        }
+
         self.query.insert(self.cursor, ch);
         self.cursor += 1;
         self.refresh();
@@ -29,6 +144,7 @@ impl Prompt {
 
     pub fn backspace(&mut self) -> bool {
         if self.cursor > 0 {
+            self.save();
             self.cursor -= 1;
             self.query.remove(self.cursor);
             self.refresh();
@@ -39,32 +155,201 @@ impl Prompt {
         false
     }
 
+    /// Kill the whole query
     pub fn clear(&mut self) {
-        self.query.clear();
+        self.kill(0, self.len());
         self.cursor = 0;
         self.refresh();
     }
 
     pub fn left(&mut self) {
+        self.typing = false;
         if self.cursor > 0 {
             self.cursor -= 1;
         }
     }
 
     pub fn right(&mut self) {
+        self.typing = false;
         if self.cursor < self.len() {
             self.cursor += 1;
         }
     }
 
+    /// Delete the character under the cursor
+    pub fn delete(&mut self) -> bool {
+        if self.cursor < self.len() {
+            self.save();
+            self.query.remove(self.cursor);
+            self.refresh();
+
+            return true;
+        }
+
+        false
+    }
+
+    /// Move the cursor to the start of the current or previous word
+    pub fn word_left(&mut self) {
+        self.typing = false;
+        self.cursor = self.previous_word_start();
+    }
+
+    /// Move the cursor to the end of the current or next word
+    pub fn word_right(&mut self) {
+        self.typing = false;
+        self.cursor = self.next_word_end();
+    }
+
+    /// Move the cursor to the start of the next word
+    pub fn word_start_right(&mut self) {
+        self.typing = false;
+        self.cursor = self.next_word_start();
+    }
+
+    /// Move the cursor to the last character of the current or next word
+    pub fn word_end_right(&mut self) {
+        self.typing = false;
+
+        let mut idx = self.cursor + 1;
+        while idx < self.len() && self.is_separator_at(idx) {
+            idx += 1;
+        }
+        while idx < self.len() && !self.is_separator_at(idx) {
+            idx += 1;
+        }
+
+        self.cursor = idx.saturating_sub(1).max(self.cursor);
+    }
+
+    /// Kill from the cursor to the start of the next word
+    pub fn delete_to_word_start(&mut self) -> bool {
+        let end = self.next_word_start();
+
+        self.kill(self.cursor, end)
+    }
+
+    /// Kill from the cursor to the start of the current or previous word
+    pub fn delete_word_backward(&mut self) -> bool {
+        let start = self.previous_word_start();
+
+        self.kill(start, self.cursor)
+    }
+
+    /// Kill from the cursor to the end of the current or next word
+    pub fn delete_word_forward(&mut self) -> bool {
+        let end = self.next_word_end();
+
+        self.kill(self.cursor, end)
+    }
+
+    /// Kill from the cursor to the end of the query
+    pub fn kill_to_end(&mut self) -> bool {
+        self.kill(self.cursor, self.len())
+    }
+
+    /// Insert the last killed text at the cursor position
+    pub fn yank(&mut self) -> bool {
+        let text = match self.kill_ring.last() {
+            Some(text) => text.clone(),
+            None => return false,
+        };
+
+        self.insert(&text);
+
+        true
+    }
+
+    /// Insert pasted text at the cursor position as a single edit.
+    ///
+    /// The query is a single line, so line breaks and tabs are replaced by spaces and any other
+    /// control character is removed
+    pub fn paste(&mut self, text: &str) -> bool {
+        let text = text
+            .replace("\r\n", " ")
+            .chars()
+            .filter_map(|ch| match ch {
+                '\r' | '\n' | '\t' => Some(' '),
+                ch if ch.is_control() => None,
+                ch => Some(ch),
+            })
+            .collect::<String>();
+
+        if text.is_empty() {
+            return false;
+        }
+
+        self.insert(&text);
+
+        true
+    }
+
+    /// Swap the character before the cursor with the one under it and move the cursor forward.
+    /// At the end of the query the last two characters are swapped
+    pub fn transpose(&mut self) -> bool {
+        if self.len() < 2 || self.cursor == 0 {
+            return false;
+        }
+
+        self.save();
+        if self.cursor == self.len() {
+            self.cursor -= 1;
+        }
+
+        self.query.swap(self.cursor - 1, self.cursor);
+        self.cursor += 1;
+        self.refresh();
+
+        true
+    }
+
+    /// Undo the last edit
+    pub fn undo(&mut self) -> bool {
+        match self.undo_stack.pop() {
+            Some(snapshot) => {
+                let current = self.snapshot();
+                self.redo_stack.push(current);
+                self.restore(snapshot);
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redo the last undone edit
+    pub fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(snapshot) => {
+                let current = self.snapshot();
+                self.undo_stack.push(current);
+                self.restore(snapshot);
+
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn cursor_at_end(&mut self) {
+        self.typing = false;
         self.cursor = self.len();
     }
 
     pub fn cursor_at_start(&mut self) {
+        self.typing = false;
         self.cursor = 0;
     }
 
+    pub fn mode(&self) -> PromptMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: PromptMode) {
+        self.typing = false;
+        self.mode = mode;
+    }
+
     pub fn cursor_until_end(&self) -> usize {
         if self.len() < self.cursor {
             0
@@ -92,6 +377,105 @@ impl Prompt {
     pub fn refresh(&mut self) {
         self.timestamp = Instant::now();
     }
+
+    fn insert(&mut self, text: &str) {
+        self.save();
+        for ch in text.chars() {
+            self.query.insert(self.cursor, ch);
+            self.cursor += 1;
+        }
+        self.refresh();
+    }
+
+    // Remove the text between start and end, keeping it in the kill ring
+    fn kill(&mut self, start: usize, end: usize) -> bool {
+        if start >= end {
+            return false;
+        }
+
+        self.save();
+        let killed = self.query.drain(start..end).collect::<String>();
+        self.kill_ring.push(killed);
+        if self.kill_ring.len() > KILL_RING_SIZE {
+            self.kill_ring.remove(0);
+        }
+
+        self.cursor = start;
+        self.refresh();
+
+        true
+    }
+
+    // Keep the current query in the undo history before editing it
+    fn save(&mut self) {
+        let snapshot = self.snapshot();
+        self.undo_stack.push(snapshot);
+        if self.undo_stack.len() > HISTORY_SIZE {
+            self.undo_stack.remove(0);
+        }
+
+        self.redo_stack.clear();
+        self.typing = false;
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            query: self.query.clone(),
+            cursor: self.cursor,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.query = snapshot.query;
+        self.cursor = snapshot.cursor;
+        self.typing = false;
+        self.refresh();
+    }
+
+    fn is_separator_at(&self, idx: usize) -> bool {
+        let mut buffer = [0; 4];
+
+        is_word_separator(self.query[idx].encode_utf8(&mut buffer))
+    }
+
+    fn previous_word_start(&self) -> usize {
+        let mut idx = self.cursor;
+
+        while idx > 0 && self.is_separator_at(idx - 1) {
+            idx -= 1;
+        }
+        while idx > 0 && !self.is_separator_at(idx - 1) {
+            idx -= 1;
+        }
+
+        idx
+    }
+
+    fn next_word_start(&self) -> usize {
+        let mut idx = self.cursor;
+
+        while idx < self.len() && !self.is_separator_at(idx) {
+            idx += 1;
+        }
+        while idx < self.len() && self.is_separator_at(idx) {
+            idx += 1;
+        }
+
+        idx
+    }
+
+    fn next_word_end(&self) -> usize {
+        let mut idx = self.cursor;
+
+        while idx < self.len() && self.is_separator_at(idx) {
+            idx += 1;
+        }
+        while idx < self.len() && !self.is_separator_at(idx) {
+            idx += 1;
+        }
+
+        idx
+    }
 }
 
 impl From<&String> for Prompt {
@@ -113,57 +497,170 @@ impl Default for Prompt {
             timestamp: Instant::now(),
             cursor: 0,
             query: vec![],
+            kill_ring: vec![],
+            undo_stack: vec![],
+            redo_stack: vec![],
+            typing: false,
+            mode: PromptMode::default(),
         }
     }
 }
 
-/// The Arc version of Letters
-pub type Text = Arc<Letters>;
+/// A line of text, the core part of the fuzzy matching algorithm.
+///
+/// There can be millions of lines in memory, so they are stored in chunks shared by many lines
+/// (see `TextBuilder`) and a Text only knows where its line is in the chunk. Cloning it is cheap
+/// and sending it to other tasks doesn't copy the line.
+///
+/// In ASCII lines every byte is a grapheme and its lowercase version is a single byte too, so
+/// nothing else is needed. Other lines keep the offsets where each grapheme starts and, only when
+/// it's different, the lowercase version of the line.
+///
+/// A chunk is freed once none of its lines are used anymore.
+#[derive(Clone)]
+pub struct Text {
+    chunk: Arc<Chunk>,
+    line: Line,
+}
+
+/// Lines stored one after the other, along with the grapheme offsets and the lowercase version
+/// of the ones that need them
+#[derive(Debug, Default)]
+struct Chunk {
+    string: String,
+    lowercase: String,
+    // Where each grapheme starts in the string, followed by the end of the line
+    offsets: Vec<u32>,
+    // Same for the graphemes of the lowercase string
+    lowercase_offsets: Vec<u32>,
+}
 
-/// Text type builder
-#[derive(Debug, Clone)]
-pub struct TextBuilder;
+/// Where a line is in its chunk
+#[derive(Debug, Clone, Copy)]
+struct Line {
+    start: u32,
+    end: u32,
+    // Number of graphemes
+    len: u32,
+    // Index of the first offset of the line. None for ASCII lines
+    offsets: Option<u32>,
+    // Index of the first lowercase offset of the line. None when it's the same as the line
+    lowercase: Option<u32>,
+}
+
+// Bytes taken by the lines of a chunk before starting a new one. A single line can be longer
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Builds Texts, storing many lines in the same chunk
+#[derive(Debug, Default)]
+pub struct TextBuilder {
+    chunk: Chunk,
+    lines: Vec<Line>,
+    texts: Vec<Text>,
+}
 
 impl TextBuilder {
+    /// A Text with its own chunk
     pub fn build(string: &str) -> Text {
-        let text: Letters = string.into();
+        let mut builder = Self::default();
+        builder.push(string);
 
-        Arc::new(text)
+        builder.finish().remove(0)
     }
-}
 
-/// The collection of letters (Graphemes) of a string.
-///
-/// These letters are the core part of the fuzzy matching algorithm.
-///
-/// This type is not used directly but through the Text type,
-/// which is an Arc wrapper around this type. We use Arc to reduce
-/// the String allocations between tasks as much as possible.
-#[derive(Debug, Clone)]
-pub struct Letters {
-    string: String,
-    graphemes: Vec<String>,
-    graphemes_lw: Vec<String>,
-}
+    /// Add a new line to the current chunk
+    pub fn push(&mut self, string: &str) {
+        if !self.lines.is_empty() && self.chunk.string.len() + string.len() > CHUNK_SIZE {
+            self.seal();
+        }
 
-impl Letters {
-    pub fn new(string: String) -> Self {
-        let graphemes = string.graphemes(true).map(String::from).collect::<Vec<_>>();
+        let chunk = &mut self.chunk;
+        let start = chunk.string.len() as u32;
+        chunk.string.push_str(string);
+        let end = chunk.string.len() as u32;
 
-        let graphemes_lw = graphemes
-            .iter()
-            .map(|s| s.to_lowercase())
-            .collect::<Vec<_>>();
+        // `\r\n` is the only ASCII grapheme with more than one byte
+        if string.is_ascii() && !string.contains("\r\n") {
+            self.lines.push(Line {
+                start,
+                end,
+                len: end - start,
+                offsets: None,
+                lowercase: None,
+            });
 
-        Self {
-            string,
-            graphemes,
-            graphemes_lw,
+            return;
+        }
+
+        let offsets = chunk.offsets.len();
+        let lowercase_start = chunk.lowercase.len();
+        let lowercase_offsets = chunk.lowercase_offsets.len();
+
+        for (offset, grapheme) in string.grapheme_indices(true) {
+            chunk.offsets.push(start + offset as u32);
+            chunk.lowercase_offsets.push(chunk.lowercase.len() as u32);
+            chunk.lowercase.push_str(&grapheme.to_lowercase());
+        }
+        chunk.offsets.push(end);
+        chunk.lowercase_offsets.push(chunk.lowercase.len() as u32);
+
+        let lowercase = if chunk.lowercase[lowercase_start..] == *string {
+            chunk.lowercase.truncate(lowercase_start);
+            chunk.lowercase_offsets.truncate(lowercase_offsets);
+
+            None
+        } else {
+            Some(lowercase_offsets as u32)
+        };
+
+        self.lines.push(Line {
+            start,
+            end,
+            len: (chunk.offsets.len() - offsets - 1) as u32,
+            offsets: Some(offsets as u32),
+            lowercase,
+        });
+    }
+
+    /// Number of lines added since the last time the texts were taken
+    pub fn len(&self) -> usize {
+        self.texts.len() + self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take the texts of all the lines added so far
+    pub fn finish(&mut self) -> Vec<Text> {
+        self.seal();
+
+        std::mem::take(&mut self.texts)
+    }
+
+    // Share the current chunk with the texts of its lines and start a new one
+    fn seal(&mut self) {
+        if self.lines.is_empty() {
+            return;
         }
+
+        let mut chunk = std::mem::take(&mut self.chunk);
+        chunk.string.shrink_to_fit();
+        chunk.lowercase.shrink_to_fit();
+        chunk.offsets.shrink_to_fit();
+        chunk.lowercase_offsets.shrink_to_fit();
+        let chunk = Arc::new(chunk);
+
+        self.texts.extend(self.lines.drain(..).map(|line| Text {
+            chunk: chunk.clone(),
+            line,
+        }));
     }
+}
 
+impl Text {
     pub fn len(&self) -> usize {
-        self.graphemes.len()
+        self.line.len as usize
     }
 
     pub fn last_index(&self) -> usize {
@@ -177,40 +674,452 @@ impl Letters {
     }
 
     pub fn grapheme_at(&self, index: usize) -> &'_ str {
-        &self.graphemes[index]
+        let line = &self.line;
+
+        match line.offsets {
+            Some(offsets) => slice(
+                &self.chunk.string,
+                &self.chunk.offsets,
+                offsets as usize + index,
+            ),
+            None => {
+                let start = line.start as usize + index;
+                &self.chunk.string[start..start + 1]
+            }
+        }
     }
 
     pub fn lowercase_grapheme_at(&self, index: usize) -> &'_ str {
-        &self.graphemes_lw[index]
+        let line = &self.line;
+
+        match line.lowercase {
+            Some(offsets) => slice(
+                &self.chunk.lowercase,
+                &self.chunk.lowercase_offsets,
+                offsets as usize + index,
+            ),
+            None if line.offsets.is_none() => {
+                let byte = self.chunk.string.as_bytes()[line.start as usize + index];
+                if byte.is_ascii_uppercase() {
+                    let idx = (byte - b'A') as usize;
+                    &ASCII_LOWERCASE[idx..idx + 1]
+                } else {
+                    self.grapheme_at(index)
+                }
+            }
+            None => self.grapheme_at(index),
+        }
     }
 
-    pub fn iter(&self) -> Iter<'_, String> {
-        self.graphemes.iter()
+    pub fn iter(&self) -> Graphemes<'_> {
+        Graphemes {
+            text: self,
+            lowercase: false,
+            range: 0..self.len(),
+        }
     }
 
-    pub fn lowercase_iter(&self) -> Iter<'_, String> {
-        self.graphemes_lw.iter()
+    pub fn lowercase_iter(&self) -> Graphemes<'_> {
+        Graphemes {
+            text: self,
+            lowercase: true,
+            range: 0..self.len(),
+        }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.string.is_empty()
+        self.len() == 0
+    }
+
+    pub fn as_str(&self) -> &str {
+        let line = &self.line;
+
+        &self.chunk.string[line.start as usize..line.end as usize]
+    }
+
+    /// Index of the grapheme with the given byte of the string
+    pub fn grapheme_index(&self, byte: usize) -> usize {
+        let line = &self.line;
+        let offsets = match line.offsets {
+            Some(offsets) => offsets as usize,
+            None => return byte,
+        };
+        let offsets = &self.chunk.offsets[offsets..=offsets + line.len as usize];
+
+        match offsets.binary_search(&(line.start + byte as u32)) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        }
     }
+
+    /// The bytes of the string, if every one of them is a grapheme (ASCII strings)
+    pub fn as_ascii(&self) -> Option<&[u8]> {
+        match self.line.offsets {
+            Some(_) => None,
+            None => Some(
+                &self.chunk.string.as_bytes()[self.line.start as usize..self.line.end as usize],
+            ),
+        }
+    }
+}
+
+const ASCII_LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+
+fn slice<'a>(string: &'a str, offsets: &[u32], index: usize) -> &'a str {
+    &string[offsets[index] as usize..offsets[index + 1] as usize]
+}
+
+/// Iterator over the graphemes of a Text, or their lowercase version
+#[derive(Debug, Clone)]
+pub struct Graphemes<'a> {
+    text: &'a Text,
+    lowercase: bool,
+    range: Range<usize>,
 }
 
-impl From<&str> for Letters {
-    fn from(string: &str) -> Self {
-        Self::new(String::from(string))
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.range.next()?;
+
+        if self.lowercase {
+            Some(self.text.lowercase_grapheme_at(index))
+        } else {
+            Some(self.text.grapheme_at(index))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
     }
 }
 
-impl From<String> for Letters {
-    fn from(string: String) -> Self {
-        Self::new(string)
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
     }
 }
 
-impl fmt::Display for Letters {
+impl fmt::Debug for Text {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.string)
+        f.debug_tuple("Text").field(&self.as_str()).finish()
+    }
+}
+
+// Everything else in the text comes from the string
+impl PartialEq for Text {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Text {}
+
+impl Hash for Text {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_test() {
+        let placeholders = [("{matches}", "12"), ("{total}", "300"), ("{spinner}", "")];
+
+        assert_eq!(
+            expand("{matches}/{total} {spinner}", &placeholders),
+            "12/300 "
+        );
+        assert_eq!(
+            expand("{{matches}} {unknown} {", &placeholders),
+            "{12} {unknown} {"
+        );
+        assert_eq!(expand("no placeholders", &placeholders), "no placeholders");
+    }
+
+    fn prompt(query: &str, cursor: usize) -> Prompt {
+        let mut prompt: Prompt = (&query.to_string()).into();
+        prompt.cursor = cursor;
+
+        prompt
+    }
+
+    fn assert_prompt(prompt: &Prompt, query: &str, cursor: usize) {
+        assert_eq!(prompt.as_string(), query);
+        assert_eq!(
+            prompt.cursor, cursor,
+            "wrong cursor position in {:?}",
+            query
+        );
+    }
+
+    #[test]
+    fn word_movement_test() {
+        let mut p = prompt("src/ui/layout.rs", 16);
+
+        p.word_left();
+        assert_prompt(&p, "src/ui/layout.rs", 14);
+        p.word_left();
+        assert_prompt(&p, "src/ui/layout.rs", 7);
+        p.word_left();
+        p.word_left();
+        p.word_left();
+        assert_prompt(&p, "src/ui/layout.rs", 0);
+
+        p.word_right();
+        assert_prompt(&p, "src/ui/layout.rs", 3);
+        p.word_right();
+        assert_prompt(&p, "src/ui/layout.rs", 6);
+    }
+
+    #[test]
+    fn vi_word_movement_test() {
+        let mut p = prompt("foo bar-baz", 0);
+
+        p.word_start_right();
+        assert_prompt(&p, "foo bar-baz", 4);
+        p.word_start_right();
+        assert_prompt(&p, "foo bar-baz", 8);
+
+        p.cursor_at_start();
+        p.word_end_right();
+        assert_prompt(&p, "foo bar-baz", 2);
+        p.word_end_right();
+        assert_prompt(&p, "foo bar-baz", 6);
+        p.word_end_right();
+        p.word_end_right();
+        assert_prompt(&p, "foo bar-baz", 10);
+
+        let mut p = prompt("foo bar-baz", 4);
+        assert!(p.delete_to_word_start());
+        assert_prompt(&p, "foo baz", 4);
+    }
+
+    #[test]
+    fn delete_words_test() {
+        let mut p = prompt("foo bar-baz", 11);
+
+        assert!(p.delete_word_backward());
+        assert_prompt(&p, "foo bar-", 8);
+        assert!(p.delete_word_backward());
+        assert_prompt(&p, "foo ", 4);
+
+        let mut p = prompt("foo bar-baz", 3);
+        assert!(p.delete_word_forward());
+        assert_prompt(&p, "foo-baz", 3);
+
+        let mut p = prompt("foo", 0);
+        assert!(!p.delete_word_backward());
+    }
+
+    #[test]
+    fn kill_and_yank_test() {
+        let mut p = prompt("foo bar", 3);
+
+        assert!(p.kill_to_end());
+        assert_prompt(&p, "foo", 3);
+
+        p.cursor_at_start();
+        assert!(p.yank());
+        assert_prompt(&p, " barfoo", 4);
+
+        p.clear();
+        assert_prompt(&p, "", 0);
+        assert!(p.yank());
+        assert_prompt(&p, " barfoo", 7);
+
+        let mut p = Prompt::default();
+        assert!(!p.yank());
+    }
+
+    #[test]
+    fn delete_forward_test() {
+        let mut p = prompt("abc", 1);
+
+        assert!(p.delete());
+        assert_prompt(&p, "ac", 1);
+
+        p.cursor_at_end();
+        assert!(!p.delete());
+    }
+
+    #[test]
+    fn transpose_test() {
+        let mut p = prompt("abcd", 1);
+        assert!(p.transpose());
+        assert_prompt(&p, "bacd", 2);
+
+        p.cursor_at_end();
+        assert!(p.transpose());
+        assert_prompt(&p, "badc", 4);
+
+        let mut p = prompt("abcd", 0);
+        assert!(!p.transpose());
+    }
+
+    #[test]
+    fn grapheme_index_test() {
+        let ascii = TextBuilder::build("foo bar");
+        assert_eq!(ascii.grapheme_index(4), 4);
+
+        // "ñ" takes two bytes and "y̆" three
+        let unicode = TextBuilder::build("ñy̆a");
+        let indexes: Vec<usize> = (0..=6).map(|byte| unicode.grapheme_index(byte)).collect();
+        assert_eq!(indexes, vec![0, 0, 1, 1, 1, 2, 3]);
+    }
+
+    #[test]
+    fn letters_test() {
+        let cases: Vec<(&str, Vec<&str>, Vec<&str>)> = vec![
+            ("", vec![], vec![]),
+            (
+                "FooBar",
+                vec!["F", "o", "o", "B", "a", "r"],
+                vec!["f", "o", "o", "b", "a", "r"],
+            ),
+            ("a\r\nB", vec!["a", "\r\n", "B"], vec!["a", "\r\n", "b"]),
+            (
+                "ñandú",
+                vec!["ñ", "a", "n", "d", "ú"],
+                vec!["ñ", "a", "n", "d", "ú"],
+            ),
+            ("ÑO", vec!["Ñ", "O"], vec!["ñ", "o"]),
+            ("e\u{301}X", vec!["e\u{301}", "X"], vec!["e\u{301}", "x"]),
+        ];
+
+        for (string, graphemes, lowercase) in cases {
+            let text = TextBuilder::build(string);
+
+            assert_eq!(text.len(), graphemes.len(), "wrong length of {:?}", string);
+            assert_eq!(text.iter().collect::<Vec<_>>(), graphemes);
+            assert_eq!(text.lowercase_iter().collect::<Vec<_>>(), lowercase);
+            assert_eq!(text.to_string(), string);
+
+            for (idx, grapheme) in graphemes.iter().enumerate() {
+                assert_eq!(&text.grapheme_at(idx), grapheme);
+                assert_eq!(&text.lowercase_grapheme_at(idx), &lowercase[idx]);
+            }
+        }
+    }
+
+    #[test]
+    fn text_builder_test() {
+        let lines = ["FooBar", "ÑO", "", "a\r\nB", "e\u{301}X", "ñandú", "baz"];
+        let mut builder = TextBuilder::default();
+        for line in &lines {
+            builder.push(line);
+        }
+        assert_eq!(builder.len(), lines.len());
+
+        let texts = builder.finish();
+        assert!(builder.is_empty());
+
+        // all the lines share the same chunk
+        assert!(texts
+            .iter()
+            .all(|text| Arc::ptr_eq(&text.chunk, &texts[0].chunk)));
+
+        for (text, line) in texts.iter().zip(&lines) {
+            let standalone = TextBuilder::build(line);
+
+            assert_eq!(text.as_str(), *line);
+            assert_eq!(text.len(), standalone.len());
+            assert_eq!(text.as_ascii(), standalone.as_ascii());
+            assert_eq!(
+                text.iter().collect::<Vec<_>>(),
+                standalone.iter().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                text.lowercase_iter().collect::<Vec<_>>(),
+                standalone.lowercase_iter().collect::<Vec<_>>()
+            );
+            for byte in 0..line.len() {
+                assert_eq!(text.grapheme_index(byte), standalone.grapheme_index(byte));
+            }
+        }
+    }
+
+    #[test]
+    fn text_builder_chunks_test() {
+        let line = "x".repeat(CHUNK_SIZE / 2);
+        let mut builder = TextBuilder::default();
+        for _ in 0..3 {
+            builder.push(&line);
+        }
+        // a line longer than a chunk gets its own chunk
+        builder.push(&"y".repeat(CHUNK_SIZE * 2));
+
+        let texts = builder.finish();
+        assert!(Arc::ptr_eq(&texts[0].chunk, &texts[1].chunk));
+        assert!(!Arc::ptr_eq(&texts[1].chunk, &texts[2].chunk));
+        assert!(!Arc::ptr_eq(&texts[2].chunk, &texts[3].chunk));
+        assert_eq!(texts[3].len(), CHUNK_SIZE * 2);
+    }
+
+    #[test]
+    fn paste_test() {
+        let mut p = prompt("foo", 3);
+
+        assert!(p.paste(" bar\r\nbaz\tqux\n\u{7}"));
+        assert_prompt(&p, "foo bar baz qux ", 16);
+
+        assert!(!p.paste("\u{7}"));
+
+        // the whole text is undone at once
+        assert!(p.undo());
+        assert_prompt(&p, "foo", 3);
+    }
+
+    #[test]
+    fn undo_and_redo_test() {
+        let mut p = Prompt::default();
+        assert!(!p.undo());
+
+        "foo".chars().for_each(|ch| p.add(ch));
+        p.add(' ');
+        p.left();
+        p.add('x');
+        assert_prompt(&p, "foox ", 4);
+
+        p.clear();
+        assert_prompt(&p, "", 0);
+
+        assert!(p.undo());
+        assert_prompt(&p, "foox ", 4);
+
+        // consecutive characters are undone at once
+        assert!(p.undo());
+        assert_prompt(&p, "foo ", 3);
+        assert!(p.undo());
+        assert_prompt(&p, "", 0);
+        assert!(!p.undo());
+
+        assert!(p.redo());
+        assert_prompt(&p, "foo ", 3);
+        assert!(p.redo());
+        assert_prompt(&p, "foox ", 4);
+
+        // a new edit drops the redo history
+        assert!(p.backspace());
+        assert!(!p.redo());
+        assert!(p.undo());
+        assert_prompt(&p, "foox ", 4);
+    }
+
+    #[test]
+    fn search_test() {
+        let mut p = Prompt::from(&"foo bar".to_string());
+        p.left();
+        p.delete_word_backward();
+
+        let search = Search::from(&p);
+        assert_eq!(search.as_string(), "foo r");
+        assert_eq!(search.cursor_until_end(), 1);
+        assert_eq!(search.mode(), PromptMode::Insert);
+        assert_eq!(search.timestamp(), p.timestamp());
     }
 }
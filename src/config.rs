@@ -1,11 +1,16 @@
 //! Create and define the main configuration through toml files and command line args
 
+pub mod bindings;
 pub mod components;
+pub mod modes;
 pub mod styling;
 
+use bindings::{Binding, Bindings};
 use components::*;
+use modes::Mode;
 
 use crate::common::Result;
+use crate::fuzzy::Algo;
 use async_std::fs;
 use async_std::os::unix::io::AsRawFd;
 use async_std::sync::Arc;
@@ -21,12 +26,27 @@ pub struct Args {
     pub full_screen: bool,
     pub inline: bool,
     pub preserve_order: bool,
+    pub reverse: bool,
+    pub no_cycle: bool,
+    pub mouse: bool,
+    pub unique: bool,
+    pub tac: bool,
+    pub with_filename: bool,
 
     // options
-    pub lines: Option<usize>,
+    pub lines: Option<Height>,
+    pub min_height: Option<usize>,
+    pub header: Option<String>,
+    pub header_lines: Option<usize>,
     pub config: Option<String>,
     pub search: Option<String>,
     pub pool: Option<usize>,
+    pub follow: Option<String>,
+    pub tiebreak: Option<Tiebreaks>,
+    pub algo: Option<Algo>,
+    pub bindings: Vec<Binding>,
+    pub modes: Vec<Mode>,
+    pub files: Vec<String>,
 }
 
 /// Arc version of Cfg
@@ -41,16 +61,42 @@ pub struct Cfg {
     pub initial_query: Option<String>,
     #[serde(default)]
     pub preserve_order: bool,
+    #[serde(default)]
+    pub tiebreak: Tiebreaks,
+    #[serde(default)]
+    pub algo: Algo,
+    #[serde(default)]
+    pub unique: bool,
+    #[serde(default)]
+    pub tac: bool,
+    #[serde(default)]
+    pub follow: Option<String>,
+    #[serde(default)]
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub with_filename: bool,
+    #[serde(default)]
+    pub no_cycle: bool,
+    #[serde(default)]
+    pub mouse: bool,
 
     #[serde(default)]
     pub prompt: PromptConfig,
     #[serde(default)]
     pub gauge: GaugeConfig,
     #[serde(default)]
+    pub header: HeaderConfig,
+    #[serde(default)]
     pub candidate: CandidateConfig,
     #[serde(default)]
     pub selection: SelectionConfig,
 
+    #[serde(default)]
+    pub modes: Vec<Mode>,
+
+    #[serde(default)]
+    pub bindings: Bindings,
+
     #[serde(default)]
     pub advanced: AdvancedConfig,
 }
@@ -127,19 +173,80 @@ impl Configurator {
                 }
             }
 
+            if let Some(min_height) = args.min_height {
+                config.screen.set_min_height(min_height);
+            }
+
+            if args.reverse {
+                config.screen.reverse_layout();
+            }
+
+            if args.no_cycle {
+                config.no_cycle = true;
+            }
+
+            if args.mouse {
+                config.mouse = true;
+            }
+
             if args.preserve_order {
                 config.preserve_order = true;
             }
 
+            if args.unique {
+                config.unique = true;
+            }
+
+            if args.tac {
+                config.tac = true;
+            }
+
+            if let Some(path) = &args.follow {
+                config.follow = Some(path.to_string());
+            }
+
+            if !args.files.is_empty() {
+                config.files = args.files.clone();
+            }
+
+            if args.with_filename {
+                config.with_filename = true;
+            }
+
+            if let Some(tiebreak) = &args.tiebreak {
+                config.tiebreak = tiebreak.clone();
+            }
+
+            if let Some(algo) = args.algo {
+                config.algo = algo;
+            }
+
             if let Some(q) = &args.search {
                 let q = q.to_string();
                 config.initial_query = Some(q);
             }
 
+            if let Some(header) = &args.header {
+                config.header.set_text(header.to_string());
+            }
+
+            if let Some(lines) = args.header_lines {
+                config.header.set_lines(lines);
+            }
+
             if let Some(pool) = args.pool {
                 config.advanced.set_pool_size(pool);
             }
 
+            // Modes from the command line replace the ones in the config file
+            if !args.modes.is_empty() {
+                config.modes = args.modes.clone();
+            }
+
+            for binding in &args.bindings {
+                config.bindings.add(binding.clone());
+            }
+
             self.config = Some(config);
         }
 
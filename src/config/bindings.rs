@@ -0,0 +1,314 @@
+//! Custom key bindings
+//!
+//! Keys can be bound to actions in the config file:
+//!
+//! ```text
+//! # toml file
+//! [bindings]
+//! ctrl-o = "execute(vim {})"
+//! alt-c = "execute-silent(echo {} | pbcopy)"
+//! ctrl-r = "reload(find {q} -type f)"
+//! ctrl-x = "clear-list"
+//! tab = "toggle-mark"
+//! ```
+//!
+//! Or with the `--bind` option, separating the key and the action with a colon:
+//!
+//! ```text
+//! $ find * -type f | scout --bind "ctrl-o:execute(vim {})"
+//! ```
+//!
+//! Keys are written as `ctrl-<char>`, `alt-<char>`, `f<number>`, a single character or the name
+//! of a special key like `enter`, `tab`, `btab`, `backspace`, `delete`, `insert`, `home`, `end`,
+//! `pgup`, `pgdn`, `up`, `down`, `left` or `right`.
+
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use termion::event::Key;
+
+#[derive(Debug)]
+pub struct ParseBindingError(String);
+
+impl fmt::Display for ParseBindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing key binding {:?}", self.0)
+    }
+}
+
+impl Error for ParseBindingError {}
+
+/// Actions that can be bound to a key
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Run a command in the terminal, suspending the UI until it finishes
+    Execute(String),
+    /// Run a command in the background, without leaving the UI
+    ExecuteSilent(String),
+    /// Replace all the lines with the output of a command
+    Reload(String),
+    /// Add the output of a command to the lines
+    Append(String),
+    /// Remove all the lines
+    ClearList,
+    /// Switch to the next mode
+    NextMode,
+    /// Sort the matches by score or keep the order of the input
+    ToggleSort,
+    /// Switch to the next matching algorithm
+    NextAlgo,
+    /// Mark the selected match, or unmark it
+    ToggleMark,
+}
+
+impl FromStr for Action {
+    type Err = ParseBindingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseBindingError(s.to_string());
+
+        // Actions with an argument look like `name(argument)`
+        let (name, argument) = match s.find('(') {
+            Some(idx) if s.ends_with(')') => (&s[..idx], Some(&s[idx + 1..s.len() - 1])),
+            Some(_) => return Err(error()),
+            None => (s, None),
+        };
+
+        match (name, argument) {
+            ("execute", Some(command)) if !command.is_empty() => {
+                Ok(Self::Execute(command.to_string()))
+            }
+            ("execute-silent", Some(command)) if !command.is_empty() => {
+                Ok(Self::ExecuteSilent(command.to_string()))
+            }
+            ("reload", Some(command)) if !command.is_empty() => {
+                Ok(Self::Reload(command.to_string()))
+            }
+            ("append", Some(command)) if !command.is_empty() => {
+                Ok(Self::Append(command.to_string()))
+            }
+            ("clear-list", None) => Ok(Self::ClearList),
+            ("next-mode", None) => Ok(Self::NextMode),
+            ("toggle-sort", None) => Ok(Self::ToggleSort),
+            ("next-algo", None) => Ok(Self::NextAlgo),
+            ("toggle-mark", None) => Ok(Self::ToggleMark),
+            _ => Err(error()),
+        }
+    }
+}
+
+/// A key and its action, written as `KEY:ACTION`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub key: Key,
+    pub action: Action,
+}
+
+impl FromStr for Binding {
+    type Err = ParseBindingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // The key can be the colon itself
+        let idx = match s.get(1..).and_then(|rest| rest.find(':')) {
+            Some(idx) => idx + 1,
+            None => return Err(ParseBindingError(s.to_string())),
+        };
+
+        Ok(Self {
+            key: parse_key(&s[..idx])?,
+            action: s[idx + 1..].parse()?,
+        })
+    }
+}
+
+/// All the custom key bindings
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bindings(HashMap<Key, Action>);
+
+impl Bindings {
+    /// Action bound to the given key
+    pub fn get(&self, key: &Key) -> Option<&Action> {
+        self.0.get(key)
+    }
+
+    pub fn add(&mut self, binding: Binding) {
+        self.0.insert(binding.key, binding.action);
+    }
+}
+
+impl<'de> Deserialize<'de> for Bindings {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct BindingsVisitor;
+
+        impl<'de> Visitor<'de> for BindingsVisitor {
+            type Value = Bindings;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a table of keys and actions")
+            }
+
+            fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+            where
+                M: MapAccess<'de>,
+            {
+                let mut bindings = Bindings::default();
+
+                while let Some((key, action)) = access.next_entry::<String, String>()? {
+                    bindings.add(Binding {
+                        key: parse_key(&key).map_err(de::Error::custom)?,
+                        action: action.parse().map_err(de::Error::custom)?,
+                    });
+                }
+
+                Ok(bindings)
+            }
+        }
+
+        deserializer.deserialize_map(BindingsVisitor)
+    }
+}
+
+fn parse_key(s: &str) -> Result<Key, ParseBindingError> {
+    let single = |rest: &str| {
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Some(ch),
+            _ => None,
+        }
+    };
+
+    let key = match s {
+        "enter" => Some(Key::Char('\n')),
+        "tab" => Some(Key::Char('\t')),
+        "btab" => Some(Key::BackTab),
+        "space" => Some(Key::Char(' ')),
+        "esc" => Some(Key::Esc),
+        "backspace" => Some(Key::Backspace),
+        "delete" => Some(Key::Delete),
+        "insert" => Some(Key::Insert),
+        "home" => Some(Key::Home),
+        "end" => Some(Key::End),
+        "pgup" => Some(Key::PageUp),
+        "pgdn" => Some(Key::PageDown),
+        "up" => Some(Key::Up),
+        "down" => Some(Key::Down),
+        "left" => Some(Key::Left),
+        "right" => Some(Key::Right),
+        s if s.starts_with("ctrl-") => single(&s[5..])
+            .filter(|ch| ch.is_ascii_lowercase())
+            .map(Key::Ctrl),
+        s if s.starts_with("alt-") => single(&s[4..]).map(Key::Alt),
+        s if s.starts_with('f') && s.len() > 1 => s[1..]
+            .parse::<u8>()
+            .ok()
+            .filter(|n| (1..=12).contains(n))
+            .map(Key::F),
+        s => single(s).map(Key::Char),
+    };
+
+    key.ok_or_else(|| ParseBindingError(s.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_binding_test() {
+        let cases = vec![
+            (
+                "ctrl-o:execute(vim {})",
+                Key::Ctrl('o'),
+                Action::Execute("vim {}".into()),
+            ),
+            (
+                "alt-c:execute-silent(echo {q}:{n})",
+                Key::Alt('c'),
+                Action::ExecuteSilent("echo {q}:{n}".into()),
+            ),
+            ("f5:execute(ls)", Key::F(5), Action::Execute("ls".into())),
+            (
+                "enter:execute(cat {})",
+                Key::Char('\n'),
+                Action::Execute("cat {}".into()),
+            ),
+            (
+                "ctrl-r:reload(find {q})",
+                Key::Ctrl('r'),
+                Action::Reload("find {q}".into()),
+            ),
+            (
+                "alt-a:append(ls)",
+                Key::Alt('a'),
+                Action::Append("ls".into()),
+            ),
+            ("ctrl-x:clear-list", Key::Ctrl('x'), Action::ClearList),
+            ("alt-m:next-mode", Key::Alt('m'), Action::NextMode),
+            ("ctrl-s:toggle-sort", Key::Ctrl('s'), Action::ToggleSort),
+            ("alt-r:next-algo", Key::Alt('r'), Action::NextAlgo),
+            ("space:toggle-mark", Key::Char(' '), Action::ToggleMark),
+            (
+                "::execute(ls)",
+                Key::Char(':'),
+                Action::Execute("ls".into()),
+            ),
+        ];
+
+        for (string, key, action) in cases {
+            assert_eq!(string.parse::<Binding>().unwrap(), Binding { key, action });
+        }
+    }
+
+    #[test]
+    fn parse_invalid_binding_test() {
+        let cases = vec![
+            "ctrl-o",
+            "ctrl-o:",
+            "ctrl-o:execute()",
+            "ctrl-o:execute(vim {}",
+            "ctrl-o:unknown",
+            "ctrl-o:reload()",
+            "ctrl-o:clear-list(ls)",
+            "ctrl-oo:execute(ls)",
+            "f13:execute(ls)",
+            "foo:execute(ls)",
+        ];
+
+        for string in cases {
+            assert!(string.parse::<Binding>().is_err(), "{:?} is valid", string);
+        }
+    }
+
+    #[test]
+    fn deserialize_bindings_test() {
+        #[derive(Deserialize)]
+        struct Test {
+            bindings: Bindings,
+        }
+
+        let test: Test = toml::from_str(
+            r#"
+            [bindings]
+            ctrl-o = "execute(vim {})"
+            alt-c = "execute-silent(echo {})"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            test.bindings.get(&Key::Ctrl('o')),
+            Some(&Action::Execute("vim {}".into()))
+        );
+        assert_eq!(
+            test.bindings.get(&Key::Alt('c')),
+            Some(&Action::ExecuteSilent("echo {}".into()))
+        );
+        assert_eq!(test.bindings.get(&Key::Ctrl('p')), None);
+    }
+}
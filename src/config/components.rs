@@ -1,14 +1,17 @@
 //! Individual components configuration
 
 use super::styling::{Rule, Style};
-use serde::Deserialize;
+use crate::fuzzy::{Algo, ParseTiebreakError, Tiebreak};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
 const DEFAULT_HEIGHT: usize = 6;
 const MIN_HEIGHT: usize = 3;
 const MIN_WIDTH: usize = 4;
 
-const DEFAULT_POOL_SIZE: usize = 50000;
-
 #[derive(Deserialize, Clone, Debug, PartialEq)]
 enum Mode {
     #[serde(rename = "full")]
@@ -29,15 +32,364 @@ impl Default for Mode {
     }
 }
 
+/// Position of the prompt in the screen
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum Layout {
+    /// Prompt at the top, followed by the gauge and the list of candidates
+    #[serde(rename = "default")]
+    Default,
+    /// Prompt at the bottom, with the best match right above the gauge
+    #[serde(rename = "reverse")]
+    Reverse,
+}
+
+impl Layout {
+    pub fn is_reverse(&self) -> bool {
+        matches!(self, Layout::Reverse)
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::Default
+    }
+}
+
+/// Lines used to draw a border around the UI
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum Border {
+    #[serde(rename = "none")]
+    None,
+    #[serde(rename = "rounded")]
+    Rounded,
+    #[serde(rename = "sharp")]
+    Sharp,
+    #[serde(rename = "double")]
+    Double,
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Border::None
+    }
+}
+
+/// Keys used to edit the query in the prompt
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum EditMode {
+    /// Readline-like keys
+    #[serde(rename = "emacs")]
+    Emacs,
+    /// Insert and normal modes like in vi
+    #[serde(rename = "vi")]
+    Vi,
+}
+
+impl EditMode {
+    pub fn is_vi(&self) -> bool {
+        matches!(self, EditMode::Vi)
+    }
+}
+
+impl Default for EditMode {
+    fn default() -> Self {
+        EditMode::Emacs
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseSpacingError;
+
+impl fmt::Display for ParseSpacingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing spacing")
+    }
+}
+
+impl Error for ParseSpacingError {}
+
+/// Space around the UI (margin) or between the border and the content (padding)
+///
+/// It follows CSS shorthand rules, so it can be defined with one, two or four numbers:
+///
+/// ```text
+/// # toml file
+/// [screen]
+/// margin = 1            # all sides
+/// padding = "0,1"       # top and bottom, left and right
+/// margin = "1,2,1,2"    # top, right, bottom, left
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Spacing {
+    pub top: usize,
+    pub right: usize,
+    pub bottom: usize,
+    pub left: usize,
+}
+
+impl Spacing {
+    pub fn new(top: usize, right: usize, bottom: usize, left: usize) -> Self {
+        Self {
+            top,
+            right,
+            bottom,
+            left,
+        }
+    }
+
+    pub fn all(size: usize) -> Self {
+        Self::new(size, size, size, size)
+    }
+
+    pub fn vertical(&self) -> usize {
+        self.top + self.bottom
+    }
+
+    pub fn horizontal(&self) -> usize {
+        self.left + self.right
+    }
+}
+
+impl FromStr for Spacing {
+    type Err = ParseSpacingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let sizes = s
+            .split(',')
+            .map(|size| size.trim().parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ParseSpacingError)?;
+
+        match sizes[..] {
+            [all] => Ok(Self::all(all)),
+            [vertical, horizontal] => Ok(Self::new(vertical, horizontal, vertical, horizontal)),
+            [top, right, bottom, left] => Ok(Self::new(top, right, bottom, left)),
+            _ => Err(ParseSpacingError),
+        }
+    }
+}
+
+struct SpacingVisitor;
+
+impl<'de> Visitor<'de> for SpacingVisitor {
+    type Value = Spacing;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a number or a list of numbers. i.e: 1, '0,1' or '1,2,1,2'")
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if value < 0 {
+            return Err(de::Error::custom(ParseSpacingError));
+        }
+
+        Ok(Spacing::all(value as usize))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Spacing {
+    fn deserialize<D>(deserializer: D) -> Result<Spacing, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(SpacingVisitor)
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseHeightError;
+
+impl fmt::Display for ParseHeightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error parsing height, use a number of lines, a percentage or a negative number"
+        )
+    }
+}
+
+impl Error for ParseHeightError {}
+
+/// Number of lines used by the UI in inline mode
+///
+/// ```text
+/// # toml file
+/// [screen]
+/// lines = 10      # exactly 10 lines
+/// lines = "40%"   # 40% of the terminal's height
+/// lines = -5      # the terminal's height minus 5 lines
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Height {
+    Lines(usize),
+    Percentage(usize),
+    Relative(usize),
+}
+
+impl Height {
+    /// Actual number of lines given the terminal's height
+    pub fn lines(&self, full_height: usize) -> usize {
+        match self {
+            Self::Lines(lines) => *lines,
+            Self::Percentage(percentage) => full_height * percentage / 100,
+            Self::Relative(lines) => full_height.saturating_sub(*lines),
+        }
+    }
+}
+
+impl FromStr for Height {
+    type Err = ParseHeightError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some(percentage) = s.strip_suffix('%') {
+            return match percentage.parse::<usize>() {
+                Ok(p) if p <= 100 => Ok(Self::Percentage(p)),
+                _ => Err(ParseHeightError),
+            };
+        }
+
+        if let Some(lines) = s.strip_prefix('-') {
+            return lines
+                .parse()
+                .map(Self::Relative)
+                .map_err(|_| ParseHeightError);
+        }
+
+        s.parse().map(Self::Lines).map_err(|_| ParseHeightError)
+    }
+}
+
+struct HeightVisitor;
+
+impl<'de> Visitor<'de> for HeightVisitor {
+    type Value = Height;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a number of lines or a percentage. i.e: 10, -5 or '40%'")
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if value < 0 {
+            Ok(Height::Relative(value.unsigned_abs() as usize))
+        } else {
+            Ok(Height::Lines(value as usize))
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Height {
+    fn deserialize<D>(deserializer: D) -> Result<Height, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(HeightVisitor)
+    }
+}
+
+/// Rules to sort candidates with the same score, in order
+///
+/// ```text
+/// # toml file
+/// tiebreak = "length,begin"
+/// ```
+///
+/// Candidates that are still equal keep the order of the input, so `index` is always the last
+/// rule even if it's not in the list.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tiebreaks(Vec<Tiebreak>);
+
+impl Tiebreaks {
+    pub fn rules(&self) -> &[Tiebreak] {
+        &self.0
+    }
+}
+
+impl FromStr for Tiebreaks {
+    type Err = ParseTiebreakError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rules = s
+            .split(',')
+            .map(|rule| rule.parse())
+            .collect::<Result<Vec<Tiebreak>, _>>()?;
+
+        Ok(Self(rules))
+    }
+}
+
+impl<'de> Deserialize<'de> for Tiebreaks {
+    fn deserialize<D>(deserializer: D) -> Result<Tiebreaks, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let rules = String::deserialize(deserializer)?;
+
+        rules.parse().map_err(de::Error::custom)
+    }
+}
+
+/// How candidates match the query: `fuzzy`, `exact`, `prefix` or `regex`
+///
+/// ```text
+/// # toml file
+/// algo = "exact"
+/// ```
+impl<'de> Deserialize<'de> for Algo {
+    fn deserialize<D>(deserializer: D) -> Result<Algo, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let algo = String::deserialize(deserializer)?;
+
+        algo.parse().map_err(de::Error::custom)
+    }
+}
+
 /// Main screen configuration options
 #[derive(Deserialize, Clone, Debug, Default)]
 pub struct ScreenConfig {
     #[serde(default)]
     mode: Mode,
+    #[serde(default)]
+    layout: Layout,
+    #[serde(default)]
+    border: Border,
+    #[serde(default)]
+    border_label: Option<String>,
+    #[serde(default)]
+    style_border: Option<Style>,
+    #[serde(default)]
+    margin: Spacing,
+    #[serde(default)]
+    padding: Spacing,
     #[serde(default, alias = "columns")]
     width: Option<usize>,
     #[serde(default, alias = "lines")]
-    height: Option<usize>,
+    height: Option<Height>,
+    #[serde(default)]
+    min_height: Option<usize>,
     #[serde(skip)]
     full_width: usize,
     #[serde(skip)]
@@ -53,14 +405,64 @@ impl ScreenConfig {
         self.mode = Mode::Full;
     }
 
+    /// Whether the UI takes the full screen or not.
+    ///
+    /// Inline mode falls back to full screen when the terminal doesn't have room for
+    /// the lines needed by the UI.
     pub fn is_full(&self) -> bool {
-        self.mode.is_full()
+        if self.mode.is_full() {
+            return true;
+        }
+
+        self.full_height > 0 && self.inline_height() > self.full_height
+    }
+
+    pub fn reverse_layout(&mut self) {
+        self.layout = Layout::Reverse;
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout.clone()
     }
 
-    pub fn set_height(&mut self, height: usize) {
+    pub fn border(&self) -> Border {
+        self.border.clone()
+    }
+
+    /// Text shown in the top border
+    pub fn border_label(&self) -> Option<String> {
+        self.border_label.clone()
+    }
+
+    /// Style for the border and its label
+    pub fn style_border(&self) -> Style {
+        match &self.style_border {
+            Some(st) => st.clone(),
+            None => Default::default(),
+        }
+    }
+
+    pub fn margin(&self) -> Spacing {
+        self.margin
+    }
+
+    pub fn padding(&self) -> Spacing {
+        self.padding
+    }
+
+    pub fn set_height(&mut self, height: Height) {
         self.height = Some(height)
     }
 
+    pub fn set_min_height(&mut self, min_height: usize) {
+        self.min_height = Some(min_height)
+    }
+
+    /// Minimum number of lines for the inline mode
+    pub fn min_height(&self) -> usize {
+        self.min_height.unwrap_or(MIN_HEIGHT)
+    }
+
     pub fn set_width(&mut self, width: usize) {
         self.width = Some(width)
     }
@@ -70,23 +472,34 @@ impl ScreenConfig {
     }
 
     pub fn width(&self) -> usize {
-        let width = match self.mode {
-            Mode::Full => self.full_width,
-            Mode::Inline => self.width.unwrap_or(self.full_width),
+        let width = if self.is_full() {
+            self.full_width
+        } else {
+            self.width.unwrap_or(self.full_width)
         };
 
         MIN_WIDTH.max(width)
     }
 
     pub fn height(&self) -> usize {
-        let height = match self.mode {
-            Mode::Full => self.full_height,
-            Mode::Inline => self.height.unwrap_or(DEFAULT_HEIGHT),
+        let height = if self.is_full() {
+            self.full_height
+        } else {
+            self.inline_height()
         };
 
         MIN_HEIGHT.max(height)
     }
 
+    fn inline_height(&self) -> usize {
+        let height = match self.height {
+            Some(height) => height.lines(self.full_height),
+            None => DEFAULT_HEIGHT,
+        };
+
+        height.max(self.min_height())
+    }
+
     pub fn set_full_size(&mut self, width: usize, height: usize) {
         self.full_width = width;
         self.full_height = height;
@@ -101,8 +514,9 @@ pub struct AdvancedConfig {
 }
 
 impl AdvancedConfig {
-    pub fn pool_size(&self) -> usize {
-        self.pool_size.unwrap_or(DEFAULT_POOL_SIZE)
+    /// Max number of lines to keep in memory. There is no limit by default
+    pub fn pool_size(&self) -> Option<usize> {
+        self.pool_size
     }
 
     pub fn set_pool_size(&mut self, pool_size: usize) {
@@ -116,6 +530,8 @@ impl AdvancedConfig {
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct PromptConfig {
     symbol: Option<String>,
+    symbol_normal: Option<String>,
+    edit_mode: Option<EditMode>,
     style: Option<Style>,
     style_symbol: Option<Style>,
 }
@@ -129,6 +545,22 @@ impl PromptConfig {
         }
     }
 
+    /// Symbol used before the query in the vi normal mode
+    pub fn symbol_normal(&self) -> String {
+        match &self.symbol_normal {
+            Some(sym) => sym.clone(),
+            None => String::from("< "),
+        }
+    }
+
+    /// Keys used to edit the query
+    pub fn edit_mode(&self) -> EditMode {
+        match &self.edit_mode {
+            Some(mode) => mode.clone(),
+            None => Default::default(),
+        }
+    }
+
     /// Query styles
     pub fn style(&self) -> Style {
         match &self.style {
@@ -153,10 +585,18 @@ impl PromptConfig {
 pub struct GaugeConfig {
     prefix: Option<String>,
     symbol: Option<String>,
+    format: Option<String>,
     style: Option<Style>,
 }
 
 impl GaugeConfig {
+    /// Text of the gauge after the prefix, with placeholders for the state of the search
+    /// (like `{matches}` or `{total}`). Without it the gauge shows the numbers separated
+    /// by the symbol
+    pub fn format(&self) -> Option<String> {
+        self.format.clone()
+    }
+
     /// Symbol used to separate current vs total numbers
     pub fn symbol(&self) -> String {
         match &self.symbol {
@@ -182,12 +622,60 @@ impl GaugeConfig {
     }
 }
 
+/// Header UI component configuration options
+///
+/// The header is a set of lines shown between the gauge and the list. They can be a static text
+/// or the first lines of the input, which are then not part of the list of candidates
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct HeaderConfig {
+    text: Option<String>,
+    lines: Option<usize>,
+    style: Option<Style>,
+}
+
+impl HeaderConfig {
+    /// Static text for the header
+    pub fn text(&self) -> Vec<String> {
+        match &self.text {
+            Some(text) => text.lines().map(String::from).collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn set_text(&mut self, text: String) {
+        self.text = Some(text);
+    }
+
+    /// Number of lines from the input used as header
+    pub fn lines(&self) -> usize {
+        self.lines.unwrap_or(0)
+    }
+
+    pub fn set_lines(&mut self, lines: usize) {
+        self.lines = Some(lines);
+    }
+
+    /// Total number of lines in the header
+    pub fn height(&self) -> usize {
+        self.text().len() + self.lines()
+    }
+
+    /// Style for the header
+    pub fn style(&self) -> Style {
+        match &self.style {
+            Some(st) => st.clone(),
+            None => Default::default(),
+        }
+    }
+}
+
 /// UI options for each candidate in the list
 ///
 /// A candidate is a string not selected
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct CandidateConfig {
     symbol: Option<String>,
+    symbol_marked: Option<String>,
     style: Option<Style>,
     style_symbol: Option<Style>,
     style_match: Option<Style>,
@@ -202,6 +690,14 @@ impl CandidateConfig {
         }
     }
 
+    /// Symbol shown before the candidate's string when it is marked
+    pub fn symbol_marked(&self) -> String {
+        match &self.symbol_marked {
+            Some(sym) => sym.clone(),
+            None => String::from("+ "),
+        }
+    }
+
     /// Style for the whole string
     pub fn style(&self) -> Style {
         match &self.style {
@@ -231,6 +727,7 @@ impl CandidateConfig {
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct SelectionConfig {
     symbol: Option<String>,
+    symbol_marked: Option<String>,
     style: Option<Style>,
     style_symbol: Option<Style>,
     style_match: Option<Style>,
@@ -245,6 +742,14 @@ impl SelectionConfig {
         }
     }
 
+    /// Symbol shown before the selection's string when it is marked
+    pub fn symbol_marked(&self) -> String {
+        match &self.symbol_marked {
+            Some(sym) => sym.clone(),
+            None => String::from("+ "),
+        }
+    }
+
     /// Style for the whole string
     pub fn style(&self) -> Style {
         match &self.style {
@@ -269,3 +774,154 @@ impl SelectionConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inline_screen(height: Option<Height>, full_height: usize) -> ScreenConfig {
+        let mut screen = ScreenConfig::default();
+        screen.inline_mode();
+        screen.set_full_size(80, full_height);
+        screen.height = height;
+
+        screen
+    }
+
+    #[test]
+    fn height_from_str_test() {
+        assert_eq!("8".parse::<Height>().unwrap(), Height::Lines(8));
+        assert_eq!("40%".parse::<Height>().unwrap(), Height::Percentage(40));
+        assert_eq!("-5".parse::<Height>().unwrap(), Height::Relative(5));
+
+        assert!("".parse::<Height>().is_err());
+        assert!("120%".parse::<Height>().is_err());
+        assert!("-a".parse::<Height>().is_err());
+    }
+
+    #[test]
+    fn height_deserialization_test() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Subject {
+            a: Height,
+            b: Height,
+            c: Height,
+        }
+
+        let content = r#"
+            a = 10
+            b = -3
+            c = "50%"
+        "#;
+        let actual: Subject = toml::from_str(content).unwrap();
+        let expected = Subject {
+            a: Height::Lines(10),
+            b: Height::Relative(3),
+            c: Height::Percentage(50),
+        };
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn tiebreaks_from_str_test() {
+        assert_eq!(
+            "length, begin,index".parse::<Tiebreaks>().unwrap().rules(),
+            &[Tiebreak::Length, Tiebreak::Begin, Tiebreak::Index]
+        );
+        assert_eq!(
+            "end".parse::<Tiebreaks>().unwrap().rules(),
+            &[Tiebreak::End]
+        );
+
+        assert!("".parse::<Tiebreaks>().is_err());
+        assert!("length,size".parse::<Tiebreaks>().is_err());
+    }
+
+    #[test]
+    fn algo_deserialization_test() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Subject {
+            a: Algo,
+            b: Algo,
+        }
+
+        let content = r#"
+            a = "regex"
+            b = "prefix"
+        "#;
+        let actual: Subject = toml::from_str(content).unwrap();
+        let expected = Subject {
+            a: Algo::Regex,
+            b: Algo::Prefix,
+        };
+
+        assert_eq!(actual, expected);
+        assert!(toml::from_str::<Subject>(r#"a = "glob""#).is_err());
+    }
+
+    #[test]
+    fn inline_height_test() {
+        assert_eq!(inline_screen(None, 40).height(), DEFAULT_HEIGHT);
+        assert_eq!(inline_screen(Some(Height::Lines(10)), 40).height(), 10);
+        assert_eq!(inline_screen(Some(Height::Percentage(50)), 40).height(), 20);
+        assert_eq!(inline_screen(Some(Height::Relative(5)), 40).height(), 35);
+        assert_eq!(
+            inline_screen(Some(Height::Relative(50)), 40).height(),
+            MIN_HEIGHT
+        );
+
+        let mut screen = inline_screen(Some(Height::Percentage(10)), 40);
+        screen.set_min_height(8);
+        assert_eq!(screen.height(), 8);
+    }
+
+    #[test]
+    fn inline_falls_back_to_full_screen_test() {
+        let screen = inline_screen(Some(Height::Lines(10)), 40);
+        assert!(!screen.is_full());
+
+        let screen = inline_screen(Some(Height::Lines(50)), 40);
+        assert!(screen.is_full());
+        assert_eq!(screen.height(), 40);
+
+        let mut screen = inline_screen(Some(Height::Percentage(10)), 12);
+        screen.set_min_height(20);
+        assert!(screen.is_full());
+    }
+
+    #[test]
+    fn spacing_from_str_test() {
+        assert_eq!("1".parse::<Spacing>().unwrap(), Spacing::all(1));
+        assert_eq!("0, 2".parse::<Spacing>().unwrap(), Spacing::new(0, 2, 0, 2));
+        assert_eq!(
+            "1,2,3,4".parse::<Spacing>().unwrap(),
+            Spacing::new(1, 2, 3, 4)
+        );
+
+        assert!("".parse::<Spacing>().is_err());
+        assert!("1,2,3".parse::<Spacing>().is_err());
+        assert!("a,b".parse::<Spacing>().is_err());
+    }
+
+    #[test]
+    fn spacing_deserialization_test() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Subject {
+            margin: Spacing,
+            padding: Spacing,
+        }
+
+        let content = r#"
+            margin = 2
+            padding = "1,2"
+        "#;
+        let actual: Subject = toml::from_str(content).unwrap();
+        let expected = Subject {
+            margin: Spacing::all(2),
+            padding: Spacing::new(1, 2, 1, 2),
+        };
+
+        assert_eq!(actual, expected);
+    }
+}
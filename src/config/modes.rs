@@ -0,0 +1,108 @@
+//! Named sources of candidates
+//!
+//! Instead of reading the lines from STDIN, scout can get them from the output of several
+//! commands, one per mode, and switch between them while it runs:
+//!
+//! ```text
+//! # toml file
+//! [[modes]]
+//! name = "files"
+//! command = "fd --type f"
+//!
+//! [[modes]]
+//! name = "branches"
+//! command = "git branch --format '%(refname:short)'"
+//! ```
+//!
+//! Or with the `--mode` option, separating the name and the command with a colon:
+//!
+//! ```text
+//! $ scout --mode files:"fd --type f" --mode branches:"git branch"
+//! ```
+
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub struct ParseModeError(String);
+
+impl fmt::Display for ParseModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing mode {:?}", self.0)
+    }
+}
+
+impl Error for ParseModeError {}
+
+/// A source of candidates with a name, written as `NAME:COMMAND`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Mode {
+    pub name: String,
+    pub command: String,
+}
+
+impl FromStr for Mode {
+    type Err = ParseModeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.find(':') {
+            Some(idx) if idx > 0 && idx < s.len() - 1 => Ok(Self {
+                name: s[..idx].to_string(),
+                command: s[idx + 1..].to_string(),
+            }),
+            _ => Err(ParseModeError(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_mode_test() {
+        let mode: Mode = "files:fd --type f".parse().unwrap();
+        assert_eq!(
+            mode,
+            Mode {
+                name: "files".into(),
+                command: "fd --type f".into()
+            }
+        );
+
+        let mode: Mode = "sorted:sort -t: -k2 list".parse().unwrap();
+        assert_eq!(mode.name, "sorted");
+        assert_eq!(mode.command, "sort -t: -k2 list");
+
+        for string in &["files", ":fd", "files:", ""] {
+            assert!(string.parse::<Mode>().is_err(), "{:?} is valid", string);
+        }
+    }
+
+    #[test]
+    fn deserialize_modes_test() {
+        #[derive(Deserialize)]
+        struct Test {
+            modes: Vec<Mode>,
+        }
+
+        let test: Test = toml::from_str(
+            r#"
+            [[modes]]
+            name = "files"
+            command = "fd"
+
+            [[modes]]
+            name = "branches"
+            command = "git branch"
+            "#,
+        )
+        .unwrap();
+
+        let names: Vec<&str> = test.modes.iter().map(|mode| mode.name.as_str()).collect();
+        assert_eq!(names, vec!["files", "branches"]);
+        assert_eq!(test.modes[1].command, "git branch");
+    }
+}
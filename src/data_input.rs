@@ -1,11 +1,31 @@
 //! Read lines from STDIN and signal when the STDIN has been consumed
+//!
+//! Lines can also come from the output of commands bound to keys, like `reload(CMD)`, that
+//! add or replace lines while the program runs, or from the commands of each mode.
+//!
+//! Instead of STDIN, the lines can come from a file that keeps growing (`--follow FILE`), like a
+//! log. Once the end of the file is reached there is no EOF: the file is checked every now and
+//! then for new lines.
+//!
+//! They can also come from the files given as arguments, one after the other. Directories are
+//! read recursively and compressed files (gzip or zstd) are decompressed on the fly.
 
 use crate::common::Result;
 use crate::events::Event;
 use async_std::channel::Sender;
-use async_std::io;
+use async_std::fs::{self, File};
+use async_std::io::{self, SeekFrom};
 use async_std::prelude::*;
-use async_std::stream;
+use async_std::process::{Command, Stdio};
+use async_std::task;
+use flate2::bufread::MultiGzDecoder;
+use std::io::{BufRead, BufReader};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Time to wait before checking if a followed file has new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(200);
 
 /// Run the data input task
 pub async fn task<R>(stdin: R, sender: Sender<Event>) -> Result<()>
@@ -14,19 +34,422 @@ where
 {
     log::trace!("starting to read input data");
 
-    let reader = io::BufReader::new(stdin);
-    let mut stream = reader
-        .lines()
-        .map(|res| res.expect("Error reading from STDIN"))
-        .filter(|line| !line.is_empty())
-        .map(Event::NewLine)
-        .chain(stream::once(Event::EOF));
-
-    while let Some(event) = stream.next().await {
-        sender.send(event).await?;
+    if let Err(e) = read_lines(stdin, &None, &sender).await {
+        log::error!("error reading STDIN: {}", e);
     }
+    sender.send(Event::EOF).await?;
 
     log::trace!("input data done");
 
     Ok(())
 }
+
+/// Read the lines of the given files, in order, and signal when all of them have been read.
+///
+/// `-` means STDIN. With `with_filename` each line starts with the name of its file and a colon,
+/// like in `grep`'s output
+pub async fn files_task<R>(
+    files: Vec<String>,
+    with_filename: bool,
+    stdin: R,
+    sender: Sender<Event>,
+) -> Result<()>
+where
+    R: io::Read + Unpin + Send + 'static,
+{
+    log::trace!("starting to read files {:?}", files);
+
+    let mut stdin = Some(stdin);
+
+    for file in files {
+        if file == "-" {
+            // STDIN can only be read once
+            if let Some(stdin) = stdin.take() {
+                let prefix = with_filename.then(|| "-".to_string());
+
+                if let Err(e) = read_lines(stdin, &prefix, &sender).await {
+                    log::error!("error reading STDIN: {}", e);
+                }
+            }
+
+            continue;
+        }
+
+        for path in walk(Path::new(&file)) {
+            let sender = sender.clone();
+            let read = task::spawn_blocking(move || {
+                read_file(&path, with_filename, &sender).map(|_| path)
+            })
+            .await;
+
+            match read {
+                Ok(path) => log::trace!("{:?} done", path),
+                Err(e) => log::error!("error reading {:?}: {}", file, e),
+            }
+        }
+    }
+
+    sender.send(Event::EOF).await?;
+    log::trace!("files done");
+
+    Ok(())
+}
+
+// The files to read from the given path: itself or, if it's a directory, all the files in it
+// (and its subdirectories) sorted by name
+fn walk(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+
+    let mut entries: Vec<PathBuf> = match path.read_dir() {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect(),
+        Err(e) => {
+            log::error!("error reading directory {:?}: {}", path, e);
+            vec![]
+        }
+    };
+    entries.sort();
+
+    entries
+        .iter()
+        // Links to directories are skipped, they might go round in circles
+        .filter(|entry| entry.is_file() || !entry.is_symlink())
+        .flat_map(|entry| walk(entry))
+        .collect()
+}
+
+// Read the lines of a file, decompressing it if needed. It blocks the thread
+fn read_file(path: &Path, with_filename: bool, sender: &Sender<Event>) -> Result<()> {
+    let mut file = BufReader::new(std::fs::File::open(path)?);
+
+    // Compressed files are recognized by their first bytes
+    let mut reader: Box<dyn BufRead> = match file.fill_buf()? {
+        [0x1f, 0x8b, ..] => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => Box::new(BufReader::new(zstd::Decoder::with_buffer(file)?)),
+        _ => Box::new(file),
+    };
+
+    let prefix = with_filename.then(|| path.display().to_string());
+    let mut buffer = vec![];
+
+    while reader.read_until(b'\n', &mut buffer)? > 0 {
+        if buffer.ends_with(b"\n") {
+            buffer.pop();
+        }
+        if buffer.ends_with(b"\r") {
+            buffer.pop();
+        }
+
+        if !buffer.is_empty() {
+            let line = String::from_utf8_lossy(&buffer).to_string();
+            sender.send_blocking(Event::NewLine(prefixed(&prefix, line)))?;
+        }
+        buffer.clear();
+    }
+
+    Ok(())
+}
+
+// Read all the lines of STDIN. Lines that aren't valid UTF-8 are decoded lossily, like the ones
+// of files
+async fn read_lines<R>(stdin: R, prefix: &Option<String>, sender: &Sender<Event>) -> Result<()>
+where
+    R: io::Read + Unpin,
+{
+    let mut reader = io::BufReader::new(stdin);
+    let mut buffer = vec![];
+
+    while reader.read_until(b'\n', &mut buffer).await? > 0 {
+        send_line(&mut buffer, prefix, sender).await?;
+    }
+
+    Ok(())
+}
+
+fn prefixed(prefix: &Option<String>, line: String) -> String {
+    match prefix {
+        Some(prefix) => format!("{}:{}", prefix, line),
+        None => line,
+    }
+}
+
+/// Read the lines of a file and keep reading the ones appended to it, like `tail -f`.
+///
+/// The engine gets an EOF the first time the end of the file is reached, so the lines that were
+/// already there show up at once. If the file gets shorter, because it was truncated, it's read
+/// again from the start. If it's replaced by another file, like when logs are rotated, the rest
+/// of the old file is read and then the new one from the start
+pub async fn follow_task(path: String, file: File, sender: Sender<Event>) -> Result<()> {
+    log::trace!("following {:?}", path);
+
+    let mut id = file_id(&file.metadata().await?);
+    let mut reader = io::BufReader::new(file);
+    let mut buffer = vec![];
+    let mut position = 0;
+    let mut eof = false;
+    let mut replaced = false;
+
+    loop {
+        let read = reader.read_until(b'\n', &mut buffer).await?;
+        position += read as u64;
+
+        if buffer.ends_with(b"\n") || (replaced && !buffer.is_empty()) {
+            send_line(&mut buffer, &None, &sender).await?;
+            continue;
+        }
+
+        // The old file is done, go on with the one that replaced it
+        if replaced {
+            log::trace!("{:?} has been replaced, reading the new file", path);
+
+            let file = File::open(&path).await?;
+            id = file_id(&file.metadata().await?);
+            reader = io::BufReader::new(file);
+            position = 0;
+            replaced = false;
+
+            continue;
+        }
+
+        // There is nothing else to read yet, any partial line waits for the rest of it
+        if !eof {
+            sender.send(Event::EOF).await?;
+            eof = true;
+        }
+        task::sleep(FOLLOW_INTERVAL).await;
+
+        match fs::metadata(&path).await {
+            Ok(metadata) if file_id(&metadata) != id => replaced = true,
+            Ok(metadata) if metadata.len() < position => {
+                log::trace!("{:?} is shorter than before, reading it again", path);
+
+                reader = io::BufReader::new(File::open(&path).await?);
+                buffer.clear();
+                position = 0;
+
+                continue;
+            }
+            // While a rotated file is not there yet, the old one is still read
+            Ok(_) | Err(_) => (),
+        }
+
+        // Once a file reaches its end it doesn't read anything else, until it moves
+        reader.seek(SeekFrom::Start(position)).await?;
+    }
+}
+
+// What identifies a file, even if it's renamed
+fn file_id(metadata: &std::fs::Metadata) -> (u64, u64) {
+    (metadata.dev(), metadata.ino())
+}
+
+// Send the line in the buffer, without its line break, and empty the buffer
+async fn send_line(
+    buffer: &mut Vec<u8>,
+    prefix: &Option<String>,
+    sender: &Sender<Event>,
+) -> Result<()> {
+    if buffer.ends_with(b"\n") {
+        buffer.pop();
+    }
+    if buffer.ends_with(b"\r") {
+        buffer.pop();
+    }
+
+    if !buffer.is_empty() {
+        let line = String::from_utf8_lossy(buffer).to_string();
+        sender.send(Event::NewLine(prefixed(prefix, line))).await?;
+    }
+    buffer.clear();
+
+    Ok(())
+}
+
+/// Run a command and read the lines from its output into the given pool.
+///
+/// The command is killed if the task is cancelled before it finishes
+pub async fn command_task(command: String, pool: usize, sender: Sender<Event>) -> Result<()> {
+    log::trace!("starting to read the output of {:?}", command);
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    sender.send(Event::CommandStart(pool)).await?;
+
+    if let Some(stdout) = child.stdout.take() {
+        let reader = io::BufReader::new(stdout);
+        let mut stream = reader
+            .lines()
+            .filter_map(|res| res.ok())
+            .filter(|line| !line.is_empty())
+            .map(|line| Event::CommandLine((pool, line)));
+
+        while let Some(event) = stream.next().await {
+            sender.send(event).await?;
+        }
+    }
+    sender.send(Event::CommandEOF(pool)).await?;
+
+    let status = child.status().await?;
+    log::trace!("output of {:?} done with {:?}", command, status);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::channel;
+    use std::io::Write;
+
+    fn line(event: Option<Event>) -> String {
+        match event {
+            Some(Event::NewLine(line)) => line,
+            other => panic!("expected a new line, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn files_task_test() {
+        let dir = std::env::temp_dir().join(format!("scout-files-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("logs")).unwrap();
+        std::fs::write(dir.join("a.txt"), "a1\r\n\na2").unwrap();
+
+        let mut gz = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        gz.write_all(b"gz1\ngz2\n").unwrap();
+        std::fs::write(dir.join("logs/b.log.gz"), gz.finish().unwrap()).unwrap();
+        let zst = zstd::encode_all(&b"zst1\n"[..], 0).unwrap();
+        std::fs::write(dir.join("logs/c.zst"), zst).unwrap();
+
+        let lines = task::block_on(async {
+            let (sender, recv) = channel::unbounded();
+            let files = vec![dir.to_string_lossy().to_string(), "-".into()];
+            let stdin = io::Cursor::new(b"in1\nin2\n".to_vec());
+
+            files_task(files, false, stdin, sender).await.unwrap();
+
+            recv.map(|event| match event {
+                Event::NewLine(line) => line,
+                other => format!("{:?}", other),
+            })
+            .collect::<Vec<String>>()
+            .await
+        });
+
+        assert_eq!(
+            lines,
+            vec!["a1", "a2", "gz1", "gz2", "zst1", "in1", "in2", "EOF"]
+        );
+
+        let lines = task::block_on(async {
+            let (sender, recv) = channel::unbounded();
+            let file = dir.join("a.txt").to_string_lossy().to_string();
+
+            files_task(vec![file], true, io::empty(), sender)
+                .await
+                .unwrap();
+
+            recv.take(2)
+                .map(|event| line(Some(event)))
+                .collect::<Vec<String>>()
+                .await
+        });
+        let file = dir.join("a.txt").display().to_string();
+        assert_eq!(lines, vec![format!("{}:a1", file), format!("{}:a2", file)]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stdin_invalid_utf8_test() {
+        let (task_events, files_events) = task::block_on(async {
+            // Lines from STDIN that aren't valid UTF-8 are decoded lossily
+            let input = b"in1\n\xff\r\nin2".to_vec();
+
+            let (sender, recv) = channel::unbounded();
+            task(io::Cursor::new(input.clone()), sender).await.unwrap();
+            let task_events = recv.collect::<Vec<Event>>().await;
+
+            let (sender, recv) = channel::unbounded();
+            files_task(vec!["-".into()], false, io::Cursor::new(input), sender)
+                .await
+                .unwrap();
+            let files_events = recv.collect::<Vec<Event>>().await;
+
+            (task_events, files_events)
+        });
+
+        for events in [task_events, files_events] {
+            let lines: Vec<&str> = events
+                .iter()
+                .filter_map(|event| match event {
+                    Event::NewLine(line) => Some(line.as_str()),
+                    _ => None,
+                })
+                .collect();
+
+            assert_eq!(lines, vec!["in1", "\u{fffd}", "in2"]);
+            assert!(matches!(events.last(), Some(Event::EOF)));
+        }
+    }
+
+    #[test]
+    fn follow_task_test() {
+        let path = std::env::temp_dir().join(format!("scout-follow-{}.log", std::process::id()));
+        std::fs::write(&path, "a\r\nb\n\npart").unwrap();
+
+        task::block_on(async {
+            let (sender, mut recv) = channel::unbounded();
+            let file = File::open(&path).await.unwrap();
+            let follow = task::spawn(follow_task(
+                path.to_string_lossy().to_string(),
+                file,
+                sender,
+            ));
+
+            assert_eq!(line(recv.next().await), "a");
+            assert_eq!(line(recv.next().await), "b");
+            assert!(matches!(recv.next().await, Some(Event::EOF)));
+
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap();
+            file.write_all(b"ial\nc\n").unwrap();
+
+            assert_eq!(line(recv.next().await), "partial");
+            assert_eq!(line(recv.next().await), "c");
+
+            // Truncated files are read again
+            std::fs::write(&path, "x\n").unwrap();
+            assert_eq!(line(recv.next().await), "x");
+
+            // Lines that are not valid UTF-8 don't stop the task
+            file.write_all(b"\xff\n").unwrap();
+            assert_eq!(line(recv.next().await), "\u{fffd}");
+
+            // Rotated files are read until the end, then the new file is read from the start
+            let rotated = path.with_extension("log.1");
+            file.write_all(b"last").unwrap();
+            std::fs::rename(&path, &rotated).unwrap();
+            std::fs::write(&path, "new1\nnew2\n").unwrap();
+            assert_eq!(line(recv.next().await), "last");
+            assert_eq!(line(recv.next().await), "new1");
+            assert_eq!(line(recv.next().await), "new2");
+            std::fs::remove_file(&rotated).unwrap();
+
+            follow.cancel().await;
+        });
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
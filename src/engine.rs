@@ -2,16 +2,214 @@
 //!
 //! This task will collect all the input from STDIN and search over them on new queries.
 //! Once a search is done all the results will be sent to the screen.
+//!
+//! Lines are searched while they arrive: every few lines, or after a short while if the input is
+//! slow, only the new lines are searched and their matches merged into the previous results. The
+//! screen is told when the input is still loading, until all the sources of lines are done, and
+//! when the pool has dropped lines because of its limit.
+//!
+//! With `--unique` lines that are already in the pool are ignored, so only the first one of them
+//! is kept. A line dropped because of the pool limit can come back later.
+//!
+//! With `--tac` the newest lines go first: every new line is added to the front of the pool and,
+//! when the pool is full, the oldest line is dropped from the back.
+//!
+//! The first lines of the input can be used as a header. These lines are sent to the screen
+//! and never get into the pool, so they can't be searched or selected.
+//!
+//! The pool can also change while the program runs, with lines from the output of commands
+//! (see `reload(CMD)` and `append(CMD)`) or when it is cleared. Once the pool has been cleared,
+//! any remaining line from STDIN is ignored: the list belongs to the commands from then on.
+//!
+//! With modes there is one pool per mode, filled by the mode's command, and searches only
+//! happen over the pool of the active mode.
+//!
+//! Searches only keep the best matches, enough to fill a few screens, and count the rest. When
+//! the person scrolls past them the screen asks for more (`LoadMore`) and the last search is
+//! done again with a bigger limit. The screen always gets an answer, even if the matches it
+//! asked for are already there.
+//!
+//! Candidates match the query with the active algorithm (see `--algo`), which can be switched
+//! while the program runs. Each query is prepared once for the algorithm (see `Matcher`) and sent
+//! to the screen with its results, to highlight the matches. While a query is not valid, like a
+//! regex in the middle of typing it, the last valid one is kept along with its results.
 
 use crate::common::{Result, Text, TextBuilder};
 use crate::config::Config;
 use crate::events::Event;
-use crate::fuzzy;
+use crate::fuzzy::{self, Matcher, Matches, Tiebreak};
 use async_std::channel::{Receiver, Sender};
+use async_std::future;
 use async_std::prelude::*;
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::mem;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 const BUFFER_LIMIT: usize = 5000;
+// Time to wait for more lines before searching the ones that already arrived
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+// Number of matches kept by a new search
+const MATCHES_LIMIT: usize = 200;
+
+/// Lines of one of the sources of candidates
+///
+/// New lines are stored in chunks (see `TextBuilder`) and only get into the pool before the next
+/// search, so the lines that arrive together share the same chunks. Duplicated lines and the
+/// pool limit are handled then.
+#[derive(Debug, Default)]
+struct Pool {
+    lines: VecDeque<Text>,
+    // Lines that arrived since they were last added to the pool
+    new_lines: TextBuilder,
+    // Max number of lines in the pool, if any
+    pool_size: Option<usize>,
+    // Lines already searched. The rest of them arrived after the last search
+    searched: usize,
+    // Whether lines have been dropped since the last search
+    dropped: bool,
+    // Results of the last search
+    matches: Matches,
+    // Commands (or STDIN) still sending lines
+    sources: usize,
+    // Whether any line has been dropped because of the pool limit
+    truncated: bool,
+    // Lines in the pool, only when duplicated lines are ignored
+    seen: Option<HashSet<Text>>,
+    // Whether new lines go to the front of the pool
+    tac: bool,
+    cleared: bool,
+}
+
+impl Pool {
+    fn new(unique: bool, tac: bool, pool_size: Option<usize>) -> Self {
+        Self {
+            seen: if unique { Some(HashSet::new()) } else { None },
+            tac,
+            pool_size,
+            ..Default::default()
+        }
+    }
+
+    /// Add a new line, it gets into the pool before the next search
+    fn push(&mut self, line: &str) {
+        self.new_lines.push(line);
+    }
+
+    /// Add the new lines to the pool
+    fn load(&mut self) {
+        for text in self.new_lines.finish() {
+            self.add(text);
+        }
+    }
+
+    fn add(&mut self, text: Text) {
+        if let Some(seen) = &mut self.seen {
+            if !seen.insert(text.clone()) {
+                log::trace!("ignoring duplicated line: {:?}", text);
+                return;
+            }
+        }
+
+        if self.tac {
+            self.lines.push_front(text);
+        } else {
+            self.lines.push_back(text);
+        }
+
+        // The pool might be full (when there is a limit of lines in memory)
+        // so we drop the oldest line
+        if let Some(pool_size) = self.pool_size {
+            if self.lines.len() > pool_size {
+                log::trace!(
+                    "pool limit ({:?}) exceeded, dropping oldest line",
+                    pool_size
+                );
+                let oldest = if self.tac {
+                    self.lines.pop_back()
+                } else {
+                    self.lines.pop_front()
+                };
+                if let (Some(oldest), Some(seen)) = (oldest, &mut self.seen) {
+                    seen.remove(&oldest);
+                }
+                self.dropped = true;
+                self.truncated = true;
+            }
+        }
+    }
+
+    /// Number of lines that arrived since the last search
+    fn pending(&self) -> usize {
+        self.lines.len().saturating_sub(self.searched) + self.new_lines.len()
+    }
+
+    fn is_outdated(&self) -> bool {
+        self.dropped || self.pending() > 0
+    }
+
+    fn clear(&mut self) {
+        self.lines.clear();
+        self.new_lines = TextBuilder::default();
+        self.searched = 0;
+        self.dropped = false;
+        self.matches = Matches::default();
+        self.sources = 0;
+        self.truncated = false;
+        if let Some(seen) = &mut self.seen {
+            seen.clear();
+        }
+        self.cleared = true;
+    }
+
+    /// Search over all the lines
+    fn search(
+        &mut self,
+        matcher: &Matcher,
+        sort: bool,
+        limit: usize,
+        tiebreak: &[Tiebreak],
+    ) -> Matches {
+        self.load();
+        self.matches = fuzzy::search(matcher, &self.lines, !sort, tiebreak, limit);
+        self.searched = self.lines.len();
+        self.dropped = false;
+
+        self.matches.clone()
+    }
+
+    /// Search over the lines that arrived since the last search and add their matches to the
+    /// previous results. If some lines have been dropped, the previous results might have
+    /// them, so everything is searched again
+    fn search_new(
+        &mut self,
+        matcher: &Matcher,
+        sort: bool,
+        limit: usize,
+        tiebreak: &[Tiebreak],
+    ) -> Matches {
+        self.load();
+        if self.dropped {
+            return self.search(matcher, sort, limit, tiebreak);
+        }
+
+        let pending = self.pending();
+        let lines = self.lines.make_contiguous();
+        let matches = mem::take(&mut self.matches);
+
+        // New lines go after the previous ones, unless they are added to the front
+        self.matches = if self.tac {
+            let more = fuzzy::search(matcher, &lines[..pending], !sort, tiebreak, limit);
+            fuzzy::merge(matcher, more, matches, !sort, tiebreak, limit)
+        } else {
+            let more = fuzzy::search(matcher, &lines[self.searched..], !sort, tiebreak, limit);
+            fuzzy::merge(matcher, matches, more, !sort, tiebreak, limit)
+        };
+        self.searched = self.lines.len();
+
+        self.matches.clone()
+    }
+}
 
 /// Run the search engine task
 pub async fn task(
@@ -22,58 +220,277 @@ pub async fn task(
     log::trace!("starting search engine");
 
     let pool_size = config.advanced.pool_size();
-    let mut pool: VecDeque<Text> = VecDeque::new();
-    let mut count = 0;
-    let mut query = String::from("");
+    let tiebreak = config.tiebreak.rules();
+    let header_lines = config.header.lines();
+    let mut header: Vec<String> = vec![];
+    // Lines from STDIN go to the first pool
+    let mut pools: Vec<Pool> = (0..config.modes.len().max(1))
+        .map(|_| Pool::new(config.unique, config.tac, pool_size))
+        .collect();
+    // Without modes the lines come from STDIN
+    if config.modes.is_empty() {
+        pools[0].sources = 1;
+    }
+    let mut active = 0;
+    // The last valid query, ready for the active algorithm
+    let mut matcher = Arc::new(Matcher::from(""));
+    let mut algo = config.algo;
+    let mut sort = !config.preserve_order;
+    let mut limit = MATCHES_LIMIT;
+    let mut loading = false;
+    let mut truncated = false;
+    let mut last_flush = Instant::now();
+
+    loop {
+        if loading != (pools[active].sources > 0) {
+            loading = !loading;
+            output_sender.send(Event::Loading(loading)).await?;
+        }
+        if truncated != pools[active].truncated {
+            truncated = !truncated;
+            output_sender.send(Event::Truncated(truncated)).await?;
+        }
+
+        // New lines wait a bit for more of them, so they are searched together
+        let event = if pools[active].is_outdated() {
+            let wait = FLUSH_INTERVAL
+                .checked_sub(last_flush.elapsed())
+                .unwrap_or_default();
+
+            match future::timeout(wait, input_recv.next()).await {
+                Ok(event) => event,
+                Err(_) => Some(Event::Ignore),
+            }
+        } else {
+            input_recv.next().await
+        };
+
+        let event = match event {
+            Some(event) => event,
+            None => break,
+        };
 
-    while let Some(event) = input_recv.next().await {
         match event {
+            Event::NewLine(s) if pools[0].cleared => {
+                log::trace!("ignoring line after clearing the pool: {:?}", s);
+            }
+            Event::NewLine(s) if header.len() < header_lines => {
+                log::trace!("header line: {:?}", s);
+
+                header.push(s);
+                output_sender.send(Event::Header(header.clone())).await?;
+            }
             Event::NewLine(s) => {
                 log::trace!("line: {:?}", s);
+                pools[0].push(&s);
+            }
+            Event::EOF if pools[0].cleared => (),
+            Event::EOF => {
+                log::trace!("all input data done");
+                pools[0].sources = 0;
 
-                // Push the new line into the main pool
-                pool.push_back(TextBuilder::build(&s));
-                count += 1;
+                if active == 0 {
+                    let matches = pools[0].search_new(&matcher, sort, limit, tiebreak);
+                    flush(matches, &pools[0], &matcher, &output_sender).await?;
+                    last_flush = Instant::now();
+                }
+            }
+            Event::CommandStart(idx) => {
+                if let Some(pool) = pools.get_mut(idx) {
+                    pool.sources += 1;
+                }
+            }
+            Event::CommandLine((idx, s)) => {
+                log::trace!("line for pool {}: {:?}", idx, s);
 
-                // The pool might be full (too many lines in memory)
-                // so we drop the first line
-                if pool.len() > pool_size {
-                    log::trace!("pool limit ({:?}) exceeded, dropping first line", pool_size);
-                    let _f = pool.pop_front();
+                if let Some(pool) = pools.get_mut(idx) {
+                    pool.push(&s);
+                }
+            }
+            Event::CommandEOF(idx) => {
+                log::trace!("command done for pool {}", idx);
+
+                if let Some(pool) = pools.get_mut(idx) {
+                    pool.sources = pool.sources.saturating_sub(1);
                 }
 
-                // We've got enough lines to refresh the search and send it
-                // to the screen
-                if count > BUFFER_LIMIT {
-                    count = 0;
-                    let matches = fuzzy::search(&query, &pool, config.preserve_order);
-                    output_sender
-                        .send(Event::Flush((matches, pool.len())))
-                        .await?;
+                if idx == active {
+                    let matches = pools[active].search_new(&matcher, sort, limit, tiebreak);
+                    flush(matches, &pools[active], &matcher, &output_sender).await?;
+                    last_flush = Instant::now();
                 }
             }
-            Event::EOF => {
-                log::trace!("all input data done");
-                let matches = fuzzy::search(&query, &pool, config.preserve_order);
-                output_sender
-                    .send(Event::Flush((matches, pool.len())))
-                    .await?;
+            Event::ClearPool(idx) => {
+                log::trace!("clearing pool {}", idx);
+
+                if let Some(pool) = pools.get_mut(idx) {
+                    pool.clear();
+                }
+
+                if idx == active {
+                    let matches = pools[active].search(&matcher, sort, limit, tiebreak);
+                    flush(matches, &pools[active], &matcher, &output_sender).await?;
+                }
+            }
+            Event::SwitchMode(idx) if idx < pools.len() => {
+                // The search over the new pool comes with the mode's query right after this
+                log::trace!("switching to pool {}", idx);
+                active = idx;
+            }
+            Event::ToggleSort => {
+                sort = !sort;
+                log::trace!("sorting matches: {}", sort);
+
+                let matches = pools[active].search(&matcher, sort, limit, tiebreak);
+                flush(matches, &pools[active], &matcher, &output_sender).await?;
             }
-            Event::Search(prompt) => {
-                query = prompt.as_string();
+            Event::LoadMore(count) if count > limit => {
+                log::trace!("loading {} matches", count);
+                limit = count;
+
+                let matches = pools[active].search(&matcher, sort, limit, tiebreak);
+                flush(matches, &pools[active], &matcher, &output_sender).await?;
+            }
+            Event::LoadMore(_) => {
+                // There is nothing else to load, but the screen waits for an answer anyway
+                let matches = pools[active].matches.clone();
+                flush(matches, &pools[active], &matcher, &output_sender).await?;
+            }
+            Event::SwitchAlgo(next) => {
+                log::trace!("matching with algorithm: {}", next);
+                // The search with the new algorithm comes with the query right after this
+                algo = next;
+            }
+            Event::Search(search) => {
+                let query = search.as_string();
+
+                // Until the query is valid the last results stay, along with their matcher
+                matcher = match Matcher::new(algo, &query) {
+                    Some(next) => Arc::new(next),
+                    None => {
+                        log::trace!("ignoring invalid query: '{}'", query);
+                        continue;
+                    }
+                };
+                limit = MATCHES_LIMIT;
                 log::trace!("performing new search: '{}'", query);
 
-                let matches = fuzzy::search(&query, &pool, config.preserve_order);
-                let results = Event::SearchDone((matches, pool.len(), prompt.timestamp()));
+                let pool = &mut pools[active];
+                let matches = pool.search(&matcher, sort, limit, tiebreak);
+                let results = Event::SearchDone((
+                    matches,
+                    pool.lines.len(),
+                    matcher.clone(),
+                    search.timestamp(),
+                ));
 
                 output_sender.send(results).await?;
             }
             Event::Done | Event::Exit => break,
             _ => (),
         };
+
+        // We've got enough new lines, or they have been waiting long enough, to refresh
+        // the search and send it to the screen
+        let pool = &mut pools[active];
+        if pool.is_outdated()
+            && (pool.pending() > BUFFER_LIMIT || last_flush.elapsed() >= FLUSH_INTERVAL)
+        {
+            let matches = pool.search_new(&matcher, sort, limit, tiebreak);
+            flush(matches, pool, &matcher, &output_sender).await?;
+            last_flush = Instant::now();
+        }
     }
 
     log::trace!("search engine done");
 
     Ok(())
 }
+
+// Send the results of a search over the pool to the screen
+async fn flush(
+    matches: Matches,
+    pool: &Pool,
+    matcher: &Arc<Matcher>,
+    output_sender: &Sender<Event>,
+) -> Result<()> {
+    output_sender
+        .send(Event::Flush((matches, pool.lines.len(), matcher.clone())))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(pool: &mut Pool) -> Vec<String> {
+        pool.load();
+        pool.lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn push_unique_lines_test() {
+        let mut pool = Pool::new(true, false, None);
+        for line in &["a", "b", "a", "c", "b"] {
+            pool.push(line);
+        }
+        assert_eq!(lines(&mut pool), vec!["a", "b", "c"]);
+
+        pool.clear();
+        pool.push("a");
+        assert_eq!(lines(&mut pool), vec!["a"]);
+
+        let mut pool = Pool::new(false, false, None);
+        for line in &["a", "b", "a"] {
+            pool.push(line);
+        }
+        assert_eq!(lines(&mut pool), vec!["a", "b", "a"]);
+    }
+
+    #[test]
+    fn push_unique_lines_with_pool_limit_test() {
+        let mut pool = Pool::new(true, false, Some(2));
+        for line in &["a", "b", "b", "c", "a", "c"] {
+            pool.push(line);
+        }
+
+        // "a" was dropped before it came back, so it's in the pool again
+        assert_eq!(lines(&mut pool), vec!["c", "a"]);
+        assert!(pool.truncated);
+    }
+
+    #[test]
+    fn push_lines_in_reverse_test() {
+        let mut pool = Pool::new(false, true, Some(3));
+        for line in &["a", "b", "c", "d"] {
+            pool.push(line);
+        }
+
+        // The oldest line is the one dropped
+        assert_eq!(lines(&mut pool), vec!["d", "c", "b"]);
+    }
+
+    #[test]
+    fn search_new_lines_in_reverse_test() {
+        let to_strings = |matches: Matches| -> Vec<String> {
+            matches.candidates.iter().map(|c| c.to_string()).collect()
+        };
+
+        for query in &["", "a"] {
+            let matcher = Matcher::from(*query);
+            let mut pool = Pool::new(false, true, None);
+            pool.push("a1");
+            pool.push("b");
+            pool.search(&matcher, false, 10, &[]);
+
+            pool.push("a2");
+            pool.push("a3");
+            let new = pool.search_new(&matcher, false, 10, &[]);
+            let all = pool.search(&matcher, false, 10, &[]);
+
+            assert_eq!(to_strings(new), to_strings(all));
+        }
+    }
+}
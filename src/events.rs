@@ -2,8 +2,9 @@
 //!
 //! All tasks communicate between them using events.
 
-use crate::common::Prompt;
-use crate::fuzzy::Candidate;
+use crate::common::Search;
+use crate::fuzzy::{Algo, Matcher, Matches};
+use std::sync::Arc;
 use std::time::Instant;
 
 #[derive(Clone, Debug)]
@@ -12,22 +13,76 @@ pub enum Event {
     NewLine(String),
     /// Signal that STDIN is done
     EOF,
+    /// A command started to send lines to the given pool
+    CommandStart(usize),
+    /// New line from the output of a command, with the pool it belongs to
+    CommandLine((usize, String)),
+    /// Signal that the output of a command is done
+    CommandEOF(usize),
 
     /// Move selection up
     Up,
     /// Move selection down
     Down,
+    /// Move selection one page up
+    PageUp,
+    /// Move selection one page down
+    PageDown,
+    /// Move selection half a page up
+    HalfPageUp,
+    /// Move selection half a page down
+    HalfPageDown,
+    /// Select the first match
+    First,
+    /// Select the last match
+    Last,
+    /// Mark the selected match, or unmark it
+    ToggleMark,
+    /// Mouse click on the given line of the terminal
+    Click(u16),
+    /// Mouse double click on the given line of the terminal
+    DoubleClick(u16),
+    /// Line of the terminal where the cursor is
+    CursorPosition(u16),
+    /// Run a command in the terminal, suspending the UI
+    Execute(String),
+    /// Run a command in the background
+    ExecuteSilent(String),
+    /// The UI is back after running a command
+    Resume,
+    /// Replace all the lines with the output of a command
+    Reload(String),
+    /// Add the output of a command to the lines
+    Append(String),
+    /// Remove all the lines
+    ClearList,
+    /// Remove all the lines of the given pool
+    ClearPool(usize),
+    /// Search over the pool of the given mode
+    SwitchMode(usize),
+    /// Match candidates with the given algorithm
+    SwitchAlgo(Algo),
+    /// Sort the matches by score or keep the order of the input
+    ToggleSort,
     /// Exit the program without selecting anything
     Exit,
     /// Exit with selection
     Done,
 
     /// Perform a new search
-    Search(Prompt),
-    /// Results from a search
-    SearchDone((Vec<Candidate>, usize, Instant)),
+    Search(Search),
+    /// Results from a search, with the size of the pool and the query used
+    SearchDone((Matches, usize, Arc<Matcher>, Instant)),
     /// Flush the screen with the given list of candidates
-    Flush((Vec<Candidate>, usize)),
+    Flush((Matches, usize, Arc<Matcher>)),
+    /// Get at least the given number of matches from the last search
+    LoadMore(usize),
+    /// Whether there are still lines to come
+    Loading(bool),
+    /// Whether lines have been dropped from the pool because of its limit
+    Truncated(bool),
+    /// Lines from STDIN used as header
+    Header(Vec<String>),
 
     /// NO-OP. Used to make some internal streams happy
     Ignore,
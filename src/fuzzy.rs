@@ -11,6 +11,7 @@
 //!
 //! [fuzzaldrinplus]: https://github.com/jeancroy/fuzz-aldrin-plus
 
+mod matcher;
 mod predicates;
 mod scoring;
 mod types;
@@ -19,48 +20,200 @@ use predicates::*;
 use scoring::*;
 use types::*;
 
-pub use types::{Candidate, Query};
+pub use matcher::Matcher;
+pub use predicates::is_word_separator;
+pub use types::{Algo, Candidate, Matches, ParseAlgoError, ParseTiebreakError, Query, Tiebreak};
 
 use crate::common::Text;
 use rayon::prelude::*;
+use std::cmp::Ordering;
 
 // Max number missed consecutive hit = ceil(MISS_COEFF * query.len()) + 5
 const MISS_COEFF: f32 = 0.75;
 
-/// Search for candidates that fuzzy-match a query
+/// Search for candidates that match a query, prepared for one of the algorithms
 ///
 /// * If the query is empty it just returns the same pool of candidates
 /// * Otherwise it will try to compute the best match for each candidate.
 /// * If `preserve_order` is not set, the candidates will be sorted from higher
-///   score to lower.
-pub fn search<'pool>(
-    q: &str,
-    pool: &'pool impl IntoParallelRefIterator<'pool, Item = &'pool Text>,
+///   score to lower. Candidates with the same score are sorted with the `tiebreak` rules,
+///   in order, and then by their position in the pool.
+///
+/// Only the best `limit` candidates are returned, along with the number of candidates that
+/// matched the query. The positions of the matches in each candidate are not computed, unless
+/// the `tiebreak` rules need them (see `positions`).
+pub fn search<'pool, P>(
+    matcher: &Matcher,
+    pool: &'pool P,
     preserve_order: bool,
-) -> Vec<Candidate> {
-    let mut matches: Vec<Candidate>;
+    tiebreak: &[Tiebreak],
+    limit: usize,
+) -> Matches
+where
+    P: IntoParallelRefIterator<'pool, Item = &'pool Text> + ?Sized,
+    P::Iter: IndexedParallelIterator,
+{
+    if matcher.is_empty() {
+        let pool = pool.par_iter();
+        let count = pool.len();
+        let candidates = pool.take(limit).map(|txt| txt.into()).collect();
+
+        return Matches { candidates, count };
+    }
 
-    if q.is_empty() {
-        matches = pool.par_iter().map(|txt| txt.into()).collect();
-    } else {
-        let query: Query = q.into();
-        matches = pool
-            .par_iter()
-            .map(|c| compute_match(&query, c))
-            .filter(|c| c.is_some())
-            .map(|c| c.unwrap())
-            .collect();
+    // Only the rules about the matches need their positions
+    let with_positions = !preserve_order
+        && tiebreak
+            .iter()
+            .take_while(|rule| **rule != Tiebreak::Index)
+            .any(|rule| matches!(rule, Tiebreak::Begin | Tiebreak::End));
+    let compare = order(tiebreak, preserve_order);
+
+    pool.par_iter()
+        .enumerate()
+        .filter_map(|(idx, subject)| {
+            matcher
+                .compute_match(subject, with_positions)
+                .map(|candidate| (idx, candidate))
+        })
+        .fold(|| TopK::new(limit, &compare), TopK::push)
+        .reduce(|| TopK::new(limit, &compare), TopK::merge)
+        .into_matches()
+}
+
+/// Merge the results of searching new lines into the results of searching the lines before them.
+///
+/// Both results must come from searching with the same `matcher`. Candidates in `matches` go before
+/// the ones in `more` when they are equal, as if all of them had been searched at once.
+pub fn merge(
+    matcher: &Matcher,
+    matches: Matches,
+    more: Matches,
+    preserve_order: bool,
+    tiebreak: &[Tiebreak],
+    limit: usize,
+) -> Matches {
+    // Without a query the candidates are not sorted
+    let compare = order(tiebreak, preserve_order || matcher.is_empty());
+    let offset = matches.candidates.len();
+    let count = matches.count + more.count;
+
+    // The position of a candidate in its results is enough to keep the order of the input
+    let mut entries: Vec<Entry> = matches
+        .candidates
+        .into_iter()
+        .enumerate()
+        .chain(
+            more.candidates
+                .into_iter()
+                .enumerate()
+                .map(|(idx, candidate)| (offset + idx, candidate)),
+        )
+        .collect();
+    entries.sort_unstable_by(|a, b| compare(a, b));
+    entries.truncate(limit);
+
+    Matches {
+        candidates: entries.into_iter().map(|(_, c)| c).collect(),
+        count,
+    }
+}
+
+/// Positions of the query's graphemes in the subject, if it matches
+pub fn positions(matcher: &Matcher, subject: &Text) -> Vec<usize> {
+    matcher
+        .compute_match(subject, true)
+        .map(|candidate| candidate.matches)
+        .unwrap_or_default()
+}
+
+// A candidate with its position in the pool
+type Entry = (usize, Candidate);
+
+// How to sort the matches: by score and the tiebreak rules, or just by their position in the pool.
+// Candidates are already sorted by position after `Index`, so the rules after it are ignored
+fn order(
+    tiebreak: &[Tiebreak],
+    preserve_order: bool,
+) -> impl Fn(&Entry, &Entry) -> Ordering + Sync + '_ {
+    move |a, b| {
+        if preserve_order {
+            return a.0.cmp(&b.0);
+        }
+
+        tiebreak
+            .iter()
+            .take_while(|rule| **rule != Tiebreak::Index)
+            .fold(b.1.cmp(&a.1), |ord, rule| {
+                ord.then_with(|| rule.compare(&a.1, &b.1))
+            })
+            .then_with(|| a.0.cmp(&b.0))
+    }
+}
 
-        if !preserve_order {
-            matches.par_sort_unstable_by(|a, b| b.cmp(a));
+// Best candidates found so far, up to the limit. New candidates are buffered and, when the
+// buffer doubles the limit, sorted to drop the worst ones
+struct TopK<'c, F> {
+    entries: Vec<Entry>,
+    count: usize,
+    limit: usize,
+    compare: &'c F,
+}
+
+impl<'c, F> TopK<'c, F>
+where
+    F: Fn(&Entry, &Entry) -> Ordering,
+{
+    fn new(limit: usize, compare: &'c F) -> Self {
+        Self {
+            entries: vec![],
+            count: 0,
+            limit,
+            compare,
         }
     }
 
-    matches
+    fn push(mut self, entry: Entry) -> Self {
+        self.count += 1;
+        self.entries.push(entry);
+        self.shrink();
+
+        self
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        self.count += other.count;
+        self.entries.extend(other.entries);
+        self.shrink();
+
+        self
+    }
+
+    fn shrink(&mut self) {
+        if self.entries.len() >= self.limit.saturating_mul(2).max(1) {
+            self.truncate();
+        }
+    }
+
+    fn truncate(&mut self) {
+        let compare = self.compare;
+        self.entries.sort_unstable_by(|a, b| compare(a, b));
+        self.entries.truncate(self.limit);
+    }
+
+    fn into_matches(mut self) -> Matches {
+        self.truncate();
+
+        Matches {
+            candidates: self.entries.into_iter().map(|(_, c)| c).collect(),
+            count: self.count,
+        }
+    }
 }
 
-/// This function will return a Candidate with the computed score and matches.
-fn compute_match(query: &Query, subject: &Text) -> Option<Candidate> {
+/// This function will return a Candidate with the computed score and, if `with_positions`
+/// is set, the positions of the matches.
+fn compute_match(query: &Query, subject: &Text, with_positions: bool) -> Option<Candidate> {
     if query.is_empty() {
         return None;
     }
@@ -100,7 +253,48 @@ fn compute_match(query: &Query, subject: &Text) -> Option<Candidate> {
     // -----------------------------------------------------------------
     // Individual characters
     // (Smith Waterman algorithm)
+    //
+    // ASCII queries and subjects compare lowercase bytes instead of graphemes
+    if let (Some(query_bytes), Some(subject_bytes)) = (query.as_ascii(), subject.as_ascii()) {
+        return smith_waterman(
+            query,
+            subject,
+            query_bytes.iter().map(u8::to_ascii_lowercase),
+            subject_bytes.iter().map(u8::to_ascii_lowercase),
+            |byte| query.contains_byte(byte),
+            acronym_score,
+            with_positions,
+        );
+    }
 
+    smith_waterman(
+        query,
+        subject,
+        query.lowercase_iter(),
+        subject.lowercase_iter(),
+        |grapheme| query.contains(grapheme),
+        acronym_score,
+        with_positions,
+    )
+}
+
+// Score each letter of the subject against each letter of the query, keeping the best path.
+// Letters are either lowercase graphemes or, for ASCII strings, lowercase bytes
+fn smith_waterman<T, Q, S, C>(
+    query: &Query,
+    subject: &Text,
+    query_letters: Q,
+    subject_letters: S,
+    in_query: C,
+    acronym_score: f32,
+    with_positions: bool,
+) -> Option<Candidate>
+where
+    T: Copy + PartialEq,
+    Q: Iterator<Item = T> + Clone,
+    S: Iterator<Item = T>,
+    C: Fn(T) -> bool,
+{
     // init
     let scored_size = score_size(query.len(), subject.len());
     // keep track of the calculated scores of query letters in the row
@@ -113,7 +307,11 @@ fn compute_match(query: &Query, subject: &Text) -> Option<Candidate> {
     let mut should_rebuild = true;
 
     // trace matrix, this is used to recover best matches positions
-    let mut trace = TraceMatrix::new(subject.len(), query.len());
+    let mut trace = if with_positions {
+        TraceMatrix::new(subject.len(), query.len())
+    } else {
+        TraceMatrix::disabled()
+    };
 
     // the algorithm works over a matrix where columns represent query letters
     // and rows are subject letters
@@ -129,11 +327,11 @@ fn compute_match(query: &Query, subject: &Text) -> Option<Candidate> {
     //    t | ^ | ^ | d |
     //    x | ^ | ^ | ^ |
     //   ----------------
-    let subject_iter = subject.lowercase_iter().enumerate();
-    'subject_loop: for (subject_index, subject_grapheme) in subject_iter {
+    let subject_iter = subject_letters.enumerate();
+    'subject_loop: for (subject_index, subject_letter) in subject_iter {
         // for every letter in the subject we move one row in the matrix
 
-        if !query.contains(subject_grapheme) {
+        if !in_query(subject_letter) {
             if should_rebuild {
                 // by resetting the consecutive_row to 0 we force the next
                 // query letter match to recalculate its consecutive score
@@ -153,8 +351,8 @@ fn compute_match(query: &Query, subject: &Text) -> Option<Candidate> {
         let mut record_miss = true;
         should_rebuild = true;
 
-        let query_iter = query.lowercase_iter().enumerate();
-        for (query_index, query_grapheme) in query_iter {
+        let query_iter = query_letters.clone().enumerate();
+        for (query_index, query_letter) in query_iter {
             // for every letter in the query we move one column in the matrix
 
             let mut consecutive_score = 0.0;
@@ -169,7 +367,7 @@ fn compute_match(query: &Query, subject: &Text) -> Option<Candidate> {
                 trace.left_at(query_index, subject_index);
             }
 
-            if query_grapheme == subject_grapheme {
+            if query_letter == subject_letter {
                 let is_start = is_start_of_word(subject, subject_index);
 
                 if consecutive_diag > 0.0 {
@@ -266,7 +464,7 @@ mod tests {
 
         for (query, string, expected) in cases {
             let subject = TextBuilder::build(string);
-            let result = compute_match(&query, &subject);
+            let result = compute_match(&query, &subject, true);
             assert!(result.is_some());
 
             let result = result.unwrap();
@@ -278,6 +476,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ascii_smith_waterman_test() {
+        let cases = vec![
+            ("fft", "FirstFactoryTest.ts"),
+            ("cre", "0core0app.rb"),
+            ("core", "0cxoxrxe0app_cxore.rb"),
+            ("ptsh", "PrintFactorySHOW"),
+        ];
+
+        for (q, s) in cases {
+            let query = Query::from(q);
+            let subject = TextBuilder::build(s);
+            let bytes = smith_waterman(
+                &query,
+                &subject,
+                query.as_ascii().unwrap().iter().map(u8::to_ascii_lowercase),
+                subject
+                    .as_ascii()
+                    .unwrap()
+                    .iter()
+                    .map(u8::to_ascii_lowercase),
+                |byte| query.contains_byte(byte),
+                0.0,
+                true,
+            )
+            .unwrap();
+            let graphemes = smith_waterman(
+                &query,
+                &subject,
+                query.lowercase_iter(),
+                subject.lowercase_iter(),
+                |grapheme| query.contains(grapheme),
+                0.0,
+                true,
+            )
+            .unwrap();
+
+            assert_eq!(bytes.score(), graphemes.score(), "{} in {}", query, subject);
+            assert_eq!(bytes.matches, graphemes.matches, "{} in {}", query, subject);
+        }
+    }
+
     #[test]
     fn compute_match_on_different_queries_test() {
         let cases: Vec<(Query, Query, &str)> = vec![
@@ -291,8 +531,8 @@ mod tests {
 
         for (a, b, string) in cases {
             let subject = TextBuilder::build(string);
-            let result_a = compute_match(&a, &subject);
-            let result_b = compute_match(&b, &subject);
+            let result_a = compute_match(&a, &subject, false);
+            let result_b = compute_match(&b, &subject, false);
 
             assert!(result_a.is_some());
             assert!(result_b.is_some());
@@ -309,4 +549,128 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn sort_with_tiebreak_test() {
+        let candidate = |string: &str, score: f32, matches: Vec<usize>| {
+            Candidate::new(&TextBuilder::build(string), score, matches)
+        };
+        let pool = [
+            candidate("abc_foo", 1.0, vec![4]),
+            candidate("foo", 1.0, vec![2]),
+            candidate("best", 2.0, vec![0]),
+            candidate("fo_o", 1.0, vec![0]),
+        ];
+        let cases: Vec<(Vec<Tiebreak>, Vec<&str>)> = vec![
+            (vec![], vec!["best", "abc_foo", "foo", "fo_o"]),
+            (
+                vec![Tiebreak::Length],
+                vec!["best", "foo", "fo_o", "abc_foo"],
+            ),
+            (
+                vec![Tiebreak::Begin],
+                vec!["best", "fo_o", "foo", "abc_foo"],
+            ),
+            (vec![Tiebreak::End], vec!["best", "foo", "abc_foo", "fo_o"]),
+            (
+                vec![Tiebreak::Index, Tiebreak::Length],
+                vec!["best", "abc_foo", "foo", "fo_o"],
+            ),
+            (
+                vec![Tiebreak::Length, Tiebreak::Begin],
+                vec!["best", "foo", "fo_o", "abc_foo"],
+            ),
+        ];
+
+        for (tiebreak, expected) in cases {
+            let compare = order(&tiebreak, false);
+            let mut top = TopK::new(pool.len(), &compare);
+            for entry in pool.iter().cloned().enumerate() {
+                top = top.push(entry);
+            }
+
+            let matches = top.into_matches();
+            let actual: Vec<String> = matches.candidates.iter().map(|c| c.to_string()).collect();
+            assert_eq!(actual, expected, "Sorting with {:?}", tiebreak);
+        }
+    }
+
+    #[test]
+    fn search_with_limit_test() {
+        let pool: Vec<Text> = (0..1000)
+            .map(|n| TextBuilder::build(&format!("file_{}.rs", n)))
+            .collect();
+        let to_strings = |matches: &Matches| -> Vec<String> {
+            matches.candidates.iter().map(|c| c.to_string()).collect()
+        };
+
+        let all = search(&"f1".into(), &pool, false, &[], usize::MAX);
+        assert_eq!(all.count, all.candidates.len());
+
+        for limit in &[0, 1, 7, 100] {
+            let top = search(&"f1".into(), &pool, false, &[], *limit);
+            assert_eq!(top.count, all.count);
+            assert_eq!(to_strings(&top), to_strings(&all)[..*limit]);
+        }
+
+        let top = search(&"".into(), &pool, false, &[], 3);
+        assert_eq!(top.count, 1000);
+        assert_eq!(
+            to_strings(&top),
+            vec!["file_0.rs", "file_1.rs", "file_2.rs"]
+        );
+    }
+
+    #[test]
+    fn merge_test() {
+        let pool: Vec<Text> = ["foo", "f_o_o", "bar", "foo_bar", "fo_o", "foo"]
+            .iter()
+            .map(|s| TextBuilder::build(s))
+            .collect();
+        let to_strings = |matches: &Matches| -> Vec<String> {
+            matches.candidates.iter().map(|c| c.to_string()).collect()
+        };
+        let tiebreak = [Tiebreak::Length];
+
+        for preserve_order in &[true, false] {
+            for query in &["", "foo", "fo", "xyz"] {
+                let matcher = Matcher::from(*query);
+                for limit in &[1, 3, 10] {
+                    let all = search(&matcher, &pool, *preserve_order, &tiebreak, *limit);
+
+                    for split in 0..pool.len() {
+                        let (first, second) = pool.split_at(split);
+                        let merged = merge(
+                            &matcher,
+                            search(&matcher, first, *preserve_order, &tiebreak, *limit),
+                            search(&matcher, second, *preserve_order, &tiebreak, *limit),
+                            *preserve_order,
+                            &tiebreak,
+                            *limit,
+                        );
+
+                        assert_eq!(merged.count, all.count);
+                        assert_eq!(
+                            to_strings(&merged),
+                            to_strings(&all),
+                            "Searching {:?} split at {}",
+                            query,
+                            split
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn positions_test() {
+        let subject = TextBuilder::build("FirstFactoryTest");
+
+        assert_eq!(
+            positions(&Matcher::Fuzzy("fft".into()), &subject),
+            vec![0, 5, 12]
+        );
+        assert!(positions(&Matcher::Fuzzy("xyz".into()), &subject).is_empty());
+    }
 }
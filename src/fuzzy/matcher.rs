@@ -0,0 +1,262 @@
+//! Other ways to match candidates besides the fuzzy algorithm
+//!
+//! The exact, prefix and regex algorithms use "smart case": they ignore the case unless the
+//! query has uppercase letters. Escaped letters in regular expressions, like `\S` or `\W`, don't
+//! count.
+
+use super::compute_match;
+use super::predicates::is_start_of_word;
+use super::types::{Algo, Candidate, Query};
+use crate::common::Text;
+use regex::{Regex, RegexBuilder};
+
+/// A query ready to match candidates with one of the algorithms
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    Fuzzy(Query),
+    Exact(Literal),
+    Prefix(Literal),
+    Regex(Regex),
+}
+
+impl Matcher {
+    /// Prepare the query for the algorithm. It's `None` when the query is not valid, like an
+    /// unfinished regular expression
+    pub fn new(algo: Algo, q: &str) -> Option<Self> {
+        let matcher = match algo {
+            Algo::Fuzzy => Self::Fuzzy(q.into()),
+            Algo::Exact => Self::Exact(Literal::new(q)),
+            Algo::Prefix => Self::Prefix(Literal::new(q)),
+            Algo::Regex => {
+                let regex = RegexBuilder::new(q)
+                    .case_insensitive(!regex_has_uppercase(q))
+                    .build()
+                    .ok()?;
+
+                Self::Regex(regex)
+            }
+        };
+
+        Some(matcher)
+    }
+
+    /// Whether the query is empty. An empty query matches every candidate
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Fuzzy(query) => query.is_empty(),
+            Self::Exact(literal) | Self::Prefix(literal) => literal.query.is_empty(),
+            Self::Regex(regex) => regex.as_str().is_empty(),
+        }
+    }
+
+    /// This function will return a Candidate with the computed score and, if `with_positions`
+    /// is set, the positions of the matches.
+    pub fn compute_match(&self, subject: &Text, with_positions: bool) -> Option<Candidate> {
+        let (start, len) = match self {
+            Self::Fuzzy(query) => return compute_match(query, subject, with_positions),
+            Self::Exact(literal) => literal.find(subject, false)?,
+            Self::Prefix(literal) => literal.find(subject, true)?,
+            Self::Regex(regex) => {
+                let found = regex.find(subject.as_str())?;
+                let start = subject.grapheme_index(found.start());
+                let end = if found.end() == found.start() {
+                    start
+                } else {
+                    subject.grapheme_index(found.end() - 1) + 1
+                };
+
+                (start, end - start)
+            }
+        };
+
+        let matches = if with_positions {
+            (start..start + len).collect()
+        } else {
+            vec![]
+        };
+
+        Some(Candidate::new(
+            subject,
+            literal_score(subject, start, len),
+            matches,
+        ))
+    }
+}
+
+/// The fuzzy algorithm is the default one
+impl From<&str> for Matcher {
+    fn from(q: &str) -> Self {
+        Self::Fuzzy(q.into())
+    }
+}
+
+/// Query used as it is, without the fuzzy algorithm
+#[derive(Debug, Clone)]
+pub struct Literal {
+    query: Query,
+    case_sensitive: bool,
+}
+
+impl Literal {
+    fn new(q: &str) -> Self {
+        Self {
+            query: q.into(),
+            case_sensitive: has_uppercase(q),
+        }
+    }
+
+    // Where the query is in the subject and how long it is, in graphemes. The first match at
+    // the start of a word is better than the first match
+    fn find(&self, subject: &Text, prefix: bool) -> Option<(usize, usize)> {
+        let len = self.query.len();
+        let last = subject.len().checked_sub(len)?;
+        let last = if prefix { 0 } else { last };
+
+        let mut positions = (0..=last).filter(|start| self.is_at(subject, *start));
+        let first = positions.next()?;
+
+        if is_start_of_word(subject, first) {
+            return Some((first, len));
+        }
+
+        let start = positions
+            .find(|start| is_start_of_word(subject, *start))
+            .unwrap_or(first);
+
+        Some((start, len))
+    }
+
+    fn is_at(&self, subject: &Text, start: usize) -> bool {
+        if let (Some(query), Some(subject)) = (self.query.as_ascii(), subject.as_ascii()) {
+            let subject = &subject[start..start + query.len()];
+
+            return if self.case_sensitive {
+                subject == query
+            } else {
+                subject.eq_ignore_ascii_case(query)
+            };
+        }
+
+        (0..self.query.len()).all(|idx| {
+            if self.case_sensitive {
+                self.query.grapheme_at(idx) == subject.grapheme_at(start + idx)
+            } else {
+                self.query.lowercase_grapheme_at(idx) == subject.lowercase_grapheme_at(start + idx)
+            }
+        })
+    }
+}
+
+// Matches at the start of a word are better, and so are the ones that cover more of the subject.
+// Empty matches can start right after the last grapheme, which is not the start of any word
+fn literal_score(subject: &Text, start: usize, len: usize) -> f32 {
+    let word = if start < subject.len() && is_start_of_word(subject, start) {
+        2.0
+    } else {
+        1.0
+    };
+
+    word + len as f32 / subject.len().max(1) as f32
+}
+
+fn has_uppercase(q: &str) -> bool {
+    q.chars().any(char::is_uppercase)
+}
+
+// Same as `has_uppercase`, skipping the characters escaped with a backslash
+fn regex_has_uppercase(q: &str) -> bool {
+    let mut escaped = false;
+
+    q.chars().any(|ch| {
+        let uppercase = !escaped && ch.is_uppercase();
+        escaped = !escaped && ch == '\\';
+
+        uppercase
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::TextBuilder;
+
+    fn positions(algo: Algo, q: &str, subject: &str) -> Option<Vec<usize>> {
+        let matcher = Matcher::new(algo, q).unwrap();
+        let subject = TextBuilder::build(subject);
+
+        matcher
+            .compute_match(&subject, true)
+            .map(|candidate| candidate.matches)
+    }
+
+    #[test]
+    fn exact_match_test() {
+        assert_eq!(positions(Algo::Exact, "bar", "foobar"), Some(vec![3, 4, 5]));
+        // The start of a word is better than the first match
+        assert_eq!(
+            positions(Algo::Exact, "bar", "foobar bar"),
+            Some(vec![7, 8, 9])
+        );
+        assert_eq!(positions(Algo::Exact, "BAR", "foo bar"), None);
+        assert_eq!(
+            positions(Algo::Exact, "bar", "FOO BAR"),
+            Some(vec![4, 5, 6])
+        );
+        assert_eq!(positions(Algo::Exact, "ñu", "el Ñu"), Some(vec![3, 4]));
+        assert_eq!(positions(Algo::Exact, "ñ", "el Ñu"), Some(vec![3]));
+        assert_eq!(positions(Algo::Exact, "Ñ", "el ñu"), None);
+        assert_eq!(positions(Algo::Exact, "b r", "bar"), None);
+    }
+
+    #[test]
+    fn prefix_match_test() {
+        assert_eq!(
+            positions(Algo::Prefix, "foo", "foobar"),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(positions(Algo::Prefix, "bar", "foobar"), None);
+        assert_eq!(positions(Algo::Prefix, "foobarbaz", "foobar"), None);
+    }
+
+    #[test]
+    fn regex_match_test() {
+        assert_eq!(positions(Algo::Regex, "o+b", "foobar"), Some(vec![1, 2, 3]));
+        assert_eq!(positions(Algo::Regex, "^b", "foobar"), None);
+        assert_eq!(positions(Algo::Regex, "ÑU$", "el ñu"), None);
+        assert_eq!(positions(Algo::Regex, "ñu$", "el Ñu"), Some(vec![3, 4]));
+        assert_eq!(positions(Algo::Regex, "x*", "foo"), Some(vec![]));
+        // empty matches at the end of the subject
+        assert_eq!(positions(Algo::Regex, "$", "foo"), Some(vec![]));
+        assert_eq!(positions(Algo::Regex, "a*$", "foo"), Some(vec![]));
+        assert_eq!(positions(Algo::Regex, "\\b$", "foo"), Some(vec![]));
+        assert_eq!(positions(Algo::Regex, "$", "ñu"), Some(vec![]));
+        assert_eq!(positions(Algo::Regex, "$", ""), Some(vec![]));
+
+        assert!(Matcher::new(Algo::Regex, "foo(").is_none());
+    }
+
+    #[test]
+    fn regex_smart_case_test() {
+        // escaped letters are classes, not uppercase letters
+        assert_eq!(positions(Algo::Regex, "O\\S", "foo"), None);
+        assert_eq!(positions(Algo::Regex, "o\\S", "FOO"), Some(vec![1, 2]));
+        assert_eq!(positions(Algo::Regex, "\\W\\D", "a-B"), Some(vec![1, 2]));
+        // an escaped backslash doesn't escape the next letter
+        assert_eq!(positions(Algo::Regex, "\\\\B", "a\\b"), None);
+        assert_eq!(positions(Algo::Regex, "\\\\B", "a\\B"), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn literal_score_test() {
+        let matcher = Matcher::new(Algo::Exact, "bar").unwrap();
+        let score = |subject: &str| {
+            matcher
+                .compute_match(&TextBuilder::build(subject), false)
+                .unwrap()
+                .score()
+        };
+
+        assert!(score("foo bar") > score("foobar"));
+        assert!(score("bar") > score("bar baz"));
+    }
+}
@@ -36,6 +36,10 @@ lazy_static! {
 
 /// Check whether a query is inside a subject or not
 pub fn is_match(query: &Query, subject: &Text) -> bool {
+    if let (Some(query), Some(subject)) = (query.as_ascii(), subject.as_ascii()) {
+        return is_ascii_match(query, subject);
+    }
+
     let query_iter = query.lowercase_iter().filter(|g| !is_optional(g));
     let mut subject_iter = subject.lowercase_iter();
 
@@ -63,6 +67,17 @@ pub fn is_match(query: &Query, subject: &Text) -> bool {
     matching == query_count
 }
 
+// Same as `is_match`, comparing bytes instead of graphemes
+fn is_ascii_match(query: &[u8], subject: &[u8]) -> bool {
+    let mut subject_iter = subject.iter().map(u8::to_ascii_lowercase);
+
+    query
+        .iter()
+        .filter(|byte| !is_optional_byte(**byte))
+        .map(u8::to_ascii_lowercase)
+        .all(|query_byte| subject_iter.any(|subject_byte| subject_byte == query_byte))
+}
+
 /// Check whether the subject has a unique acronym of the given size
 pub fn is_a_unique_acronym(subject: &Text, acronym_size: usize) -> bool {
     let mut count = 0;
@@ -107,6 +122,13 @@ pub fn is_start_of_word(subject: &Text, position: usize) -> bool {
 
     let prev_position = position - 1;
 
+    if let Some(bytes) = subject.as_ascii() {
+        let (current, prev) = (bytes[position], bytes[prev_position]);
+
+        return is_separator_byte(prev) // (b)
+            || (current.is_ascii_uppercase() && !prev.is_ascii_uppercase()); // (c)
+    }
+
     let current_grapheme = subject.grapheme_at(position);
     let prev_grapheme = subject.grapheme_at(prev_position);
 
@@ -128,6 +150,13 @@ pub fn is_end_of_word(subject: &Text, position: usize) -> bool {
 
     let next_position = position + 1;
 
+    if let Some(bytes) = subject.as_ascii() {
+        let (current, next) = (bytes[position], bytes[next_position]);
+
+        return is_separator_byte(next) // (b)
+            || (!current.is_ascii_uppercase() && next.is_ascii_uppercase()); // (c)
+    }
+
     let current_grapheme = subject.grapheme_at(position);
     let next_grapheme = subject.grapheme_at(next_position);
 
@@ -145,6 +174,16 @@ fn is_optional(grapheme: &str) -> bool {
     OPTIONAL_GRAPHEMES.contains(grapheme)
 }
 
+/// Same as `is_word_separator` with the byte of an ASCII string
+pub fn is_separator_byte(byte: u8) -> bool {
+    matches!(byte, b' ' | b'.' | b'-' | b'_' | b'/' | b'\\')
+}
+
+// ASCII version of `is_optional`
+fn is_optional_byte(byte: u8) -> bool {
+    matches!(byte, b' ' | b':' | b'-' | b'_' | b'/' | b'\\')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -182,6 +221,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ascii_and_unicode_predicates_agree_test() {
+        let subjects = vec![
+            "FactoryFiles",
+            "factory files",
+            "src/ui/layout.rs",
+            "FFiles_and-more:things",
+            "a\\b c",
+        ];
+        let queries = vec!["ff", "FaFi", "src/lay", "s-u_l", "files:", "zz", "f f", ""];
+
+        for string in subjects {
+            // A non ASCII letter at the end uses the generic version of the predicates
+            let ascii = TextBuilder::build(string);
+            let unicode = TextBuilder::build(&format!("{}ñ", string));
+            assert!(ascii.as_ascii().is_some());
+            assert!(unicode.as_ascii().is_none());
+
+            for position in 0..ascii.last_index() {
+                assert_eq!(
+                    is_start_of_word(&ascii, position),
+                    is_start_of_word(&unicode, position),
+                    "start of word at {} in {:?}",
+                    position,
+                    string
+                );
+                assert_eq!(
+                    is_end_of_word(&ascii, position),
+                    is_end_of_word(&unicode, position),
+                    "end of word at {} in {:?}",
+                    position,
+                    string
+                );
+            }
+
+            for query in &queries {
+                let query: Query = (*query).into();
+                assert_eq!(
+                    is_match(&query, &ascii),
+                    is_match(&query, &unicode),
+                    "matching {} in {:?}",
+                    query,
+                    string
+                );
+            }
+        }
+    }
+
     #[test]
     fn is_a_unique_acronym_test() {
         let cases = vec![
@@ -44,11 +44,74 @@ pub fn score_acronyms(query: &Query, subject: &Text) -> Option<AcronymResult> {
         return None;
     }
 
-    let mut matches = vec![];
-    let mut count = 0;
-    let mut sep_count = 0;
-    let mut sum_position = 0;
-    let mut same_case = 0;
+    let acronym = match (query.as_ascii(), subject.as_ascii()) {
+        (Some(query_bytes), Some(subject_bytes)) => {
+            ascii_acronym(query_bytes, subject_bytes, subject)
+        }
+        _ => acronym(query, subject),
+    };
+
+    if acronym.count < 2 {
+        return None;
+    }
+
+    let full_world = if acronym.count == query.len() {
+        // the query doesn't have any separator so it might be
+        // the unique acronym inside subject
+        is_a_unique_acronym(subject, acronym.count)
+    } else {
+        false
+    };
+    let score = score_pattern(
+        acronym.count,
+        query.len(),
+        acronym.same_case,
+        true,
+        full_world,
+    );
+
+    if score <= 0.0 {
+        return None;
+    }
+
+    let position = acronym.sum_position as f32 / acronym.count as f32;
+
+    Some(AcronymResult::new(
+        score,
+        position,
+        acronym.count + acronym.sep_count,
+        acronym.matches,
+    ))
+}
+
+// Query graphemes found at the start of the words of the subject, in order
+#[derive(Debug, Default, PartialEq)]
+struct Acronym {
+    matches: Vec<usize>,
+    count: usize,
+    sep_count: usize,
+    sum_position: usize,
+    same_case: usize,
+}
+
+impl Acronym {
+    fn push(&mut self, index: usize, same_case: bool) {
+        // we don't need to trace back the matches
+        // we only return the acronym score if the number of
+        // acronym's matches equals the query length, with means
+        // the number of matches will equal that length as well
+        self.matches.push(index);
+        self.sum_position += index;
+        self.count += 1;
+
+        if same_case {
+            self.same_case += 1;
+        }
+    }
+}
+
+fn acronym(query: &Query, subject: &Text) -> Acronym {
+    let mut acronym = Acronym::default();
 
     let query_iter = query.lowercase_iter().enumerate();
     let mut subject_iter = subject.lowercase_iter().enumerate();
@@ -66,23 +129,13 @@ pub fn score_acronyms(query: &Query, subject: &Text) -> Option<AcronymResult> {
             if query_grapheme == subject_grapheme {
                 if is_word_separator(query_grapheme) {
                     // separators don't score points, but we keep track of them
-                    sep_count += 1;
+                    acronym.sep_count += 1;
 
                     break 'subject_loop;
                 } else if is_start_of_word(subject, index) {
                     // only count graphemes that are start of a word
-                    sum_position += index;
-                    count += 1;
-
-                    // we don't need to trace back the matches
-                    // we only return the acronym score if the number of
-                    // acronym's matches equals the query length, with means
-                    // the number of matches will equal that length as well
-                    matches.push(index);
-
-                    if query.grapheme_at(qindex) == subject.grapheme_at(index) {
-                        same_case += 1;
-                    }
+                    let same_case = query.grapheme_at(qindex) == subject.grapheme_at(index);
+                    acronym.push(index, same_case);
 
                     break 'subject_loop;
                 }
@@ -90,31 +143,33 @@ pub fn score_acronyms(query: &Query, subject: &Text) -> Option<AcronymResult> {
         }
     }
 
-    if count < 2 {
-        return None;
-    }
+    acronym
+}
 
-    let full_world = if count == query.len() {
-        // the query doesn't have any separator so it might be
-        // the unique acronym inside subject
-        is_a_unique_acronym(subject, count)
-    } else {
-        false
-    };
-    let score = score_pattern(count, query.len(), same_case, true, full_world);
+// Same as `acronym`, comparing bytes instead of graphemes
+fn ascii_acronym(query: &[u8], subject_bytes: &[u8], subject: &Text) -> Acronym {
+    let mut acronym = Acronym::default();
+    let mut subject_iter = subject_bytes.iter().enumerate();
 
-    if score <= 0.0 {
-        return None;
-    }
+    for query_byte in query {
+        for (index, subject_byte) in &mut subject_iter {
+            if !query_byte.eq_ignore_ascii_case(subject_byte) {
+                continue;
+            }
 
-    let position = sum_position as f32 / count as f32;
+            if is_separator_byte(*query_byte) {
+                acronym.sep_count += 1;
 
-    Some(AcronymResult::new(
-        score,
-        position,
-        count + sep_count,
-        matches,
-    ))
+                break;
+            } else if is_start_of_word(subject, index) {
+                acronym.push(index, query_byte == subject_byte);
+
+                break;
+            }
+        }
+    }
+
+    acronym
 }
 
 /// Calculate the score of an exact match, if any
@@ -370,6 +425,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ascii_acronym_test() {
+        let cases = vec![
+            ("fft", "FirstFactoryTests"),
+            ("ff/t", "FirstFactory/Tests"),
+            ("FfT", "first_factory_tests.rs"),
+            ("iae", "FirstFactoryTests.html"),
+            ("a.b", "xa.b"),
+        ];
+
+        for (q, s) in cases {
+            let query = Query::from(q);
+            let subject = TextBuilder::build(s);
+
+            assert_eq!(
+                ascii_acronym(
+                    query.as_ascii().unwrap(),
+                    subject.as_ascii().unwrap(),
+                    &subject
+                ),
+                acronym(&query, &subject),
+                "Expected the same acronym of {} in {}",
+                query,
+                subject
+            );
+        }
+    }
+
     #[test]
     fn score_exact_match_with_no_results_test() {
         let cases = vec![
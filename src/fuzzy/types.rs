@@ -3,8 +3,10 @@
 use crate::common::{Text, TextBuilder};
 use std::cmp::Ordering;
 use std::collections::{HashSet, VecDeque};
+use std::error::Error;
 use std::fmt;
 use std::ops::Deref;
+use std::str::FromStr;
 
 /// Search query representation.
 ///
@@ -12,19 +14,48 @@ use std::ops::Deref;
 #[derive(Debug, Clone)]
 pub struct Query {
     text: Text,
-    set: HashSet<String>,
+    set: GraphemeSet,
+}
+
+/// Lowercase graphemes of the query, to quickly discard the ones of the subject that are not
+/// in it. ASCII queries use a bitmask instead of hashing strings
+#[derive(Debug, Clone)]
+enum GraphemeSet {
+    Ascii(u128),
+    Unicode(HashSet<String>),
 }
 
 impl Query {
     pub fn new(text: &Text) -> Self {
         let text = text.clone();
-        let set = text.lowercase_iter().cloned().collect();
+        let set = match text.as_ascii() {
+            Some(bytes) => GraphemeSet::Ascii(
+                bytes
+                    .iter()
+                    .fold(0, |mask, byte| mask | 1 << byte.to_ascii_lowercase()),
+            ),
+            None => GraphemeSet::Unicode(text.lowercase_iter().map(String::from).collect()),
+        };
 
         Self { text, set }
     }
 
     pub fn contains(&self, grapheme: &str) -> bool {
-        self.set.contains(grapheme)
+        match &self.set {
+            GraphemeSet::Ascii(mask) => match grapheme.as_bytes() {
+                [byte] if byte.is_ascii() => mask & (1 << byte) != 0,
+                _ => false,
+            },
+            GraphemeSet::Unicode(set) => set.contains(grapheme),
+        }
+    }
+
+    /// Same as `contains` with the lowercase byte of an ASCII subject
+    pub fn contains_byte(&self, byte: u8) -> bool {
+        match &self.set {
+            GraphemeSet::Ascii(mask) => byte.is_ascii() && mask & (1 << byte) != 0,
+            GraphemeSet::Unicode(_) => false,
+        }
     }
 }
 
@@ -104,7 +135,7 @@ impl fmt::Display for Candidate {
 
 impl Ord for Candidate {
     fn cmp(&self, other: &Candidate) -> Ordering {
-        match self.partial_cmp(other) {
+        match self.score.partial_cmp(&other.score) {
             Some(ord) => ord,
             // let's just assume that if two subject's can't be compared
             // they are equal
@@ -115,7 +146,7 @@ impl Ord for Candidate {
 
 impl PartialOrd for Candidate {
     fn partial_cmp(&self, other: &Candidate) -> Option<Ordering> {
-        self.score.partial_cmp(&other.score)
+        Some(self.cmp(other))
     }
 }
 
@@ -127,6 +158,156 @@ impl PartialEq for Candidate {
     }
 }
 
+/// Results of a search
+#[derive(Debug, Clone, Default)]
+pub struct Matches {
+    /// The best candidates, sorted. There might be fewer than `count`
+    pub candidates: Vec<Candidate>,
+    /// How many candidates matched the query
+    pub count: usize,
+}
+
+impl From<Vec<Candidate>> for Matches {
+    fn from(candidates: Vec<Candidate>) -> Self {
+        let count = candidates.len();
+
+        Self { candidates, count }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseTiebreakError(String);
+
+impl fmt::Display for ParseTiebreakError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unknown tiebreak {:?}, use length, begin, end or index",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseTiebreakError {}
+
+/// How to sort candidates with the same score
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tiebreak {
+    /// Shorter candidates first
+    Length,
+    /// Candidates with the first match closer to the beginning first
+    Begin,
+    /// Candidates with the last match closer to the end first
+    End,
+    /// Candidates in the same order as the input
+    Index,
+}
+
+impl Tiebreak {
+    /// Compare two candidates with the same score. Candidates that are still equal are sorted
+    /// by index, so `Index` never decides by itself
+    pub fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering {
+        // Distance from the last match to the end of the candidate
+        let to_end = |c: &Candidate| c.len() - c.matches.last().map_or(0, |idx| idx + 1);
+
+        match self {
+            Self::Length => a.len().cmp(&b.len()),
+            Self::Begin => a.matches.first().cmp(&b.matches.first()),
+            Self::End => to_end(a).cmp(&to_end(b)),
+            Self::Index => Ordering::Equal,
+        }
+    }
+}
+
+impl FromStr for Tiebreak {
+    type Err = ParseTiebreakError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "length" => Ok(Self::Length),
+            "begin" => Ok(Self::Begin),
+            "end" => Ok(Self::End),
+            "index" => Ok(Self::Index),
+            _ => Err(ParseTiebreakError(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseAlgoError(String);
+
+impl fmt::Display for ParseAlgoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unknown algorithm {:?}, use fuzzy, exact, prefix or regex",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseAlgoError {}
+
+/// How candidates match the query
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algo {
+    /// The query's letters appear in the candidate, in order
+    Fuzzy,
+    /// The candidate contains the query
+    Exact,
+    /// The candidate starts with the query
+    Prefix,
+    /// The query is a regular expression that matches the candidate
+    Regex,
+}
+
+impl Algo {
+    /// The algorithm that goes after this one when switching between them
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Fuzzy => Self::Exact,
+            Self::Exact => Self::Prefix,
+            Self::Prefix => Self::Regex,
+            Self::Regex => Self::Fuzzy,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Fuzzy => "fuzzy",
+            Self::Exact => "exact",
+            Self::Prefix => "prefix",
+            Self::Regex => "regex",
+        }
+    }
+}
+
+impl Default for Algo {
+    fn default() -> Self {
+        Self::Fuzzy
+    }
+}
+
+impl FromStr for Algo {
+    type Err = ParseAlgoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "fuzzy" => Ok(Self::Fuzzy),
+            "exact" => Ok(Self::Exact),
+            "prefix" => Ok(Self::Prefix),
+            "regex" => Ok(Self::Regex),
+            _ => Err(ParseAlgoError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Algo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 #[derive(Debug)]
 pub struct AcronymResult {
     pub score: f32,
@@ -167,6 +348,8 @@ enum Movement {
 }
 
 /// Keep track of the best query matches and get the best matches positions
+///
+/// When only the score is needed the matrix is empty and nothing is tracked
 #[derive(Debug)]
 pub struct TraceMatrix {
     columns: usize,
@@ -180,7 +363,18 @@ impl TraceMatrix {
         Self { columns, matrix }
     }
 
+    pub fn disabled() -> Self {
+        Self {
+            columns: 0,
+            matrix: vec![],
+        }
+    }
+
     pub fn traceback(self, x: usize, y: usize) -> Vec<usize> {
+        if self.matrix.is_empty() {
+            return vec![];
+        }
+
         let mut row = y;
         let mut column = x;
         let mut position = row * self.columns + column;
@@ -236,7 +430,38 @@ impl TraceMatrix {
     }
 
     fn set(&mut self, x: usize, y: usize, mv: Movement) {
+        if self.matrix.is_empty() {
+            return;
+        }
+
         let position = y * self.columns + x;
         self.matrix[position] = mv;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_contains_test() {
+        let ascii: Query = "FoO bar".into();
+        let unicode: Query = "FoÑ bar".into();
+
+        for grapheme in &["f", "o", " ", "b", "a", "r"] {
+            assert!(ascii.contains(grapheme), "{:?} not in {}", grapheme, ascii);
+            assert!(
+                unicode.contains(grapheme),
+                "{:?} not in {}",
+                grapheme,
+                unicode
+            );
+        }
+
+        for grapheme in &["F", "x", "ñ", "\r\n", ""] {
+            assert!(!ascii.contains(grapheme), "{:?} in {}", grapheme, ascii);
+        }
+        assert!(unicode.contains("ñ"));
+        assert!(!unicode.contains("Ñ"));
+    }
+}
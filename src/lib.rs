@@ -1,9 +1,13 @@
+// The enums with a default variant keep their hand-written `Default` implementations
+#![allow(clippy::derivable_impls)]
+
 extern crate log;
 #[macro_use]
 extern crate lazy_static;
 
 #[macro_use]
 mod macros;
+pub mod command;
 pub mod common;
 pub mod config;
 pub mod data_input;
@@ -16,33 +16,94 @@ const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
 const HELP: &str = r#"Your friendly fuzzy finder
 
 USAGE:
-    scout [FLAGS] [OPTIONS]
+    scout [FLAGS] [OPTIONS] [FILE]...
 
 FLAGS:
     -f, --full-screen    Show scout in full screen (default)
     -h, --help           Prints help information
     -i, --inline         Show scout under the current line
+        --mouse          Use the mouse to select and scroll
+        --no-cycle       Do not go to the other end of the list when moving past the
+                         first or last match
     -p, --preserve-order Do not sort the result by score
+    -r, --reverse        Show the prompt at the bottom of the screen
+        --tac            Show the last input lines first
+        --with-filename  Start each line with the name of its file when reading
+                         files
+    -u, --unique         Ignore input lines that are already in the list
     -v, --version        Prints version information
 
 OPTIONS:
     -c, --config <FILE>     Uses a custom config file
-    -l, --lines <LINES>     Number of lines to display in inline mode, including prompt
+    -l, --lines <LINES>     Number of lines to display in inline mode, including prompt.
+                            It can be a number of lines (10), a percentage of the
+                            terminal's height (40%) or the terminal's height minus
+                            the given lines (-5)
+        --min-height <LINES>
+                            Minimum number of lines in inline mode. If the terminal
+                            doesn't have enough room scout will use the full screen
     -s, --search <QUERY>    Start searching with the given query
-    -p, --pool <SIZE>       Advanced: size of the pool of candidates to keep in memory.
-                            Default is 50000. Note that increasing this number might
-                            result in the program using too much memory
+        --header <TEXT>     Show the given text between the gauge and the list
+        --header-lines <N>  Use the first N input lines as a header. They can't be
+                            searched or selected
+        --bind <KEY:ACTION> Run the action with the given key. It can be used more than
+                            once. Actions are `execute(CMD)`, to run a command in the
+                            terminal and come back, `execute-silent(CMD)`, to run it
+                            in the background, `reload(CMD)` and `append(CMD)`, to
+                            replace the list with the command's output or add it to
+                            the list, `clear-list` and `toggle-mark`. Commands can
+                            use the placeholders {} (selection), {q} (query), {+}
+                            (marked matches) and {n} (index of the selection)
+        --mode <NAME:CMD>   Read the candidates from the output of the command
+                            instead of STDIN. It can be used more than once, and
+                            `Tab` (or the `next-mode` action) switches between modes
+        --follow <FILE>     Read the candidates from the file instead of STDIN and
+                            keep reading the lines appended to it, like `tail -f`.
+                            It can't be used with modes or files
+        --tiebreak <RULES>  Comma separated rules to sort matches with the same score:
+                            length, begin, end or index. Matches that are still tied
+                            keep the order of the input
+        --algo <ALGO>       How matches are found: fuzzy (default), exact, prefix or
+                            regex. Exact, prefix and regex ignore the case unless the
+                            query has uppercase letters
+    -p, --pool <SIZE>       Advanced: max number of candidates to keep in memory. When
+                            there are more, the oldest ones are dropped. There is no
+                            limit by default
+
+ARGS:
+    <FILE>...    Read the candidates from the files instead of STDIN. Use - for
+                 STDIN. Directories are read recursively and gzip or zstd files
+                 are decompressed
 
 SUPPORTED KEYS:
     - Enter to select the current highlighted match and print it to STDOUT
     - ^u to clear the prompt
     - ^n or Down arrow key to select the next match
     - ^p or Up arrow key to select the previous match
+    - PageDown and PageUp keys to move one page down or up
+    - Alt-n and Alt-p to move half a page down or up
+    - Alt-< and Alt-> to select the first or last match
     - ^e to go to the end of the prompt
     - ^a to go to the beginning of the prompt
     - Left arrow key to move the cursor to the left in the prompt
     - Right arrow key to move the cursor to the right in the prompt
+    - Alt-b and Alt-f to move the cursor one word backward or forward
+    - Delete to remove the character under the cursor
+    - ^w to remove the word before the cursor and Alt-d to remove the word after it
+    - ^k to remove everything from the cursor to the end of the prompt
+    - ^y to insert the last removed text
+    - ^t to swap the character before the cursor with the one under it
+    - ^_ (or ^/) to undo the last change in the prompt and Alt-/ to redo it
+    - Alt-r to switch between the fuzzy, exact, prefix and regex algorithms
+    - Shift-Tab to mark or unmark the selected match, for the {+} placeholder
     - ESC to quit without selecting a match
+    - Click on a match to select it, double click to select it and print it to STDOUT
+    - Mouse wheel to move the selection up and down
+
+    With `edit_mode = "vi"` in the [prompt] config section, ESC goes to normal mode
+    where h, l, w, b, e, 0 and $ move the cursor, x, D, dw, dd, cw, cc and C edit
+    the query, u and ^r undo and redo, j and k move the selection, i, a, I and A go
+    back to insert mode and ESC quits.
 
 EXAMPLES:
     $ find * -type f | scout
@@ -53,6 +114,24 @@ EXAMPLES:
     # Use a custom config file
     $ find * -type f | scout --config="./config.toml"
 
+    # Preview files with less and come back to the list
+    $ find * -type f | scout --bind "ctrl-o:execute(less {})"
+
+    # Switch between files and git branches with Tab
+    $ scout --mode files:"fd --type f" --mode branches:"git branch"
+
+    # Pick a process keeping the column titles visible
+    $ ps aux | scout --header-lines 1 | awk '{ print $2 }'
+
+    # Pick a line from a set of files, compressed or not
+    $ scout --with-filename src/ logs/app.log.gz
+
+    # Search with a regular expression
+    $ find * -type f | scout --algo regex --search '\.rs$'
+
+    # Filter the lines of a log while they are written
+    $ scout --follow /var/log/app.log
+
     # Select a git branch and check it out with an inline menu
     $ git branch --sort=-committerdate| cut -c 3- | scout -i -p | xargs git checkout"#;
 
@@ -158,19 +237,39 @@ fn parse_args() -> std::result::Result<Args, pico_args::Error> {
         full_screen: pargs.contains(["-f", "--full-screen"]),
         inline: pargs.contains(["-i", "--inline"]),
         preserve_order: pargs.contains(["-p", "--preserve-order"]),
+        reverse: pargs.contains(["-r", "--reverse"]),
+        no_cycle: pargs.contains("--no-cycle"),
+        mouse: pargs.contains("--mouse"),
+        unique: pargs.contains(["-u", "--unique"]),
+        tac: pargs.contains("--tac"),
+        with_filename: pargs.contains("--with-filename"),
 
         // options
         search,
         lines: pargs.opt_value_from_str(["-l", "--lines"])?,
+        min_height: pargs.opt_value_from_str("--min-height")?,
+        header: pargs.opt_value_from_str("--header")?,
+        header_lines: pargs.opt_value_from_str("--header-lines")?,
         config: pargs.opt_value_from_str(["-c", "--config"])?,
         pool: pargs.opt_value_from_str(["-p", "--pool"])?,
+        follow: pargs.opt_value_from_str("--follow")?,
+        tiebreak: pargs.opt_value_from_str("--tiebreak")?,
+        algo: pargs.opt_value_from_str("--algo")?,
+        bindings: pargs.values_from_str("--bind")?,
+        modes: pargs.values_from_str("--mode")?,
+        files: vec![],
     };
 
-    let remaining = pargs.finish();
-    if !remaining.is_empty() {
-        eprintln!("Error: unknown command line arguments: {:?}.", remaining);
+    // Whatever is left are the files to read, unless they look like options
+    let (files, unknown): (Vec<String>, Vec<String>) = pargs
+        .finish()
+        .into_iter()
+        .map(|arg| arg.to_string_lossy().to_string())
+        .partition(|arg| arg == "-" || !arg.starts_with('-'));
+    if !unknown.is_empty() {
+        eprintln!("Error: unknown command line arguments: {:?}.", unknown);
         process::exit(2);
     }
 
-    Ok(args)
+    Ok(Args { files, ..args })
 }
@@ -14,31 +14,88 @@
 //!
 //! * You can use `Up` and `Down` keys to move through the list of candidates
 //! * `<C-p>` does the same the Up key and `<C-n>` as the Down key
+//! * `PageUp` and `PageDown` move one page at a time
+//! * `<A-p>` and `<A-n>` move half a page at a time
+//! * `<A-<>` selects the first candidate and `<A->>` the last one
 //! * `Backspace` will remove the character behind the cursor
 //!
 //! ### Moving around the prompt
 //!
 //! * You can use the `Left` and `Right` keys to move the cursor in the prompt
 //! * `<C-e>` will go to the end of the prompt and `<C-a>` to the beginning
+//! * `<A-b>` and `<A-f>` move the cursor one word backward or forward
+//!
+//! ### Editing the prompt
+//!
 //! * `<C-u>` clears the current query
+//! * `Delete` will remove the character under the cursor
+//! * `<C-w>` will remove the word behind the cursor and `<A-d>` the word after it
+//! * `<C-k>` will remove everything from the cursor to the end of the prompt
+//! * `<C-y>` will insert the last removed text (except for single characters)
+//! * `<C-t>` will swap the character behind the cursor with the one under it
+//! * `<C-_>` (or `<C-/>`) will undo the last change and `<A-/>` will redo it
+//!
+//! The prompt can also be edited with vi-like keys, see the `vi` module.
+//!
+//! ### Switching modes
+//!
+//! * With more than one mode, `Tab` switches to the next mode (unless `Tab` is bound to something
+//!   else). Each mode keeps its own query
+//!
+//! ### Switching the matching algorithm
+//!
+//! * `<A-r>` switches to the next algorithm: fuzzy, exact, prefix and regex (unless `<A-r>` is
+//!   bound to something else)
+//!
+//! ### Marking candidates
+//!
+//! * `<S-Tab>` marks the selected candidate, or unmarks it (unless `<S-Tab>` is bound to
+//!   something else). Commands bound to keys get the marked candidates with the `{+}`
+//!   placeholder
 //!
 //! ### Selecting a candidate and exiting the program
 //! * `Enter` will select the current candidate
 //! * `Esc` will exit the program without making a selection
+//!
+//! ### Using the mouse
+//!
+//! With the `--mouse` flag (or `mouse = true` in the config file):
+//!
+//! * Clicking on a candidate selects it and double clicking selects it and exits the program
+//! * The mouse wheel moves the selection up and down
+
+mod decoder;
+mod vi;
 
 use crate::common::{Prompt, Result};
+use crate::config::bindings::Action;
 use crate::config::Config;
 use crate::events::Event;
-use async_std::channel::Sender;
+use async_std::channel::{Receiver, Sender};
 use async_std::io;
 use async_std::prelude::*;
-use termion::event::Key;
-use termion::input::TermRead;
+use decoder::{Decoder, Input};
+use std::time::{Duration, Instant};
+use termion::event::{Event as TermEvent, Key, MouseButton, MouseEvent};
+
+// Incomplete sequences are kept between reads, so the buffer size only limits how much is
+// decoded at once
+const BUFFER_SIZE: usize = 1024;
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+// How long to wait for the rest of an escape sequence before taking it as the `Esc` key
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+// Key to switch to the next mode, when there is more than one
+const NEXT_MODE_KEY: Key = Key::Char('\t');
+// Key to switch to the next matching algorithm
+const NEXT_ALGO_KEY: Key = Key::Alt('r');
+// Key to mark the selected candidate
+const TOGGLE_MARK_KEY: Key = Key::BackTab;
 
 /// Run the person's input task
 pub async fn task<R>(
     config: Config,
     mut input: R,
+    screen_recv: Receiver<Event>,
     engine_sender: Sender<Event>,
     screen_sender: Sender<Event>,
 ) -> Result<()>
@@ -50,24 +107,160 @@ where
     let mut buffer;
     let mut query_updated: bool;
     let mut prompt: Prompt;
+    let mut last_click: Option<(Instant, u16)> = None;
+    let mut operator: Option<char> = None;
+    let mut decoder = Decoder::default();
+    // The screen asks for the cursor position when it sets up the inline UI with the mouse
+    let cursor_requests = !config.screen.is_full() && config.mouse;
+    let vi_mode = config.prompt.edit_mode().is_vi();
+    // The query of each mode is kept while other modes are active
+    let mut prompts: Vec<Prompt> = vec![Default::default(); config.modes.len().max(1)];
+    let mut mode = 0;
+    let next_mode = Action::NextMode;
+    let next_algo = Action::NextAlgo;
+    let toggle_mark = Action::ToggleMark;
+    let mut algo = config.algo;
+
+    if cursor_requests {
+        decoder.expect_cursor_position();
+    }
 
     if let Some(q) = &config.initial_query {
         prompt = q.into();
 
-        engine_sender.send(Event::Search(prompt.clone())).await?;
-        screen_sender.send(Event::Search(prompt.clone())).await?;
+        engine_sender.send(Event::Search((&prompt).into())).await?;
+        screen_sender.send(Event::Search((&prompt).into())).await?;
     } else {
         prompt = Default::default();
     }
 
     'event: loop {
-        buffer = vec![0; 10];
+        buffer = vec![0; BUFFER_SIZE];
         query_updated = false;
 
-        let num = input.read(&mut buffer).await?;
-        let keys = keys(&mut buffer, num);
+        let inputs = if decoder.is_waiting() {
+            match io::timeout(ESCAPE_TIMEOUT, input.read(&mut buffer)).await {
+                Ok(num) => decoder.decode(&buffer[..num]),
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => decoder.flush(),
+                Err(e) => return Err(e.into()),
+            }
+        } else {
+            let num = input.read(&mut buffer).await?;
+            decoder.decode(&buffer[..num])
+        };
+
+        for input in inputs {
+            let key = match input {
+                Input::Term(TermEvent::Key(key)) => key,
+                Input::Term(TermEvent::Mouse(mouse)) => {
+                    if let Some(event) = mouse_event(mouse, &mut last_click) {
+                        screen_sender.send(event).await?;
+                    }
+                    continue;
+                }
+                Input::Term(TermEvent::Unsupported(_)) => continue,
+                Input::CursorPosition(line) => {
+                    screen_sender.send(Event::CursorPosition(line)).await?;
+                    continue;
+                }
+                Input::Paste(text) => {
+                    query_updated |= prompt.paste(&text);
+                    continue;
+                }
+            };
+
+            let action = config.bindings.get(&key).or_else(|| {
+                if key == NEXT_MODE_KEY && prompts.len() > 1 {
+                    Some(&next_mode)
+                } else if key == NEXT_ALGO_KEY {
+                    Some(&next_algo)
+                } else if key == TOGGLE_MARK_KEY {
+                    Some(&toggle_mark)
+                } else {
+                    None
+                }
+            });
+
+            if let Some(action) = action {
+                // Make sure the screen knows the latest query before running anything
+                if query_updated {
+                    screen_sender.send(Event::Search((&prompt).into())).await?;
+                    engine_sender.send(Event::Search((&prompt).into())).await?;
+                    query_updated = false;
+                }
+
+                match action {
+                    Action::Execute(command) => {
+                        screen_sender.send(Event::Execute(command.clone())).await?;
+
+                        // The command takes over the terminal, so nothing can be read from it
+                        // until the command is done
+                        while !matches!(screen_recv.recv().await?, Event::Resume) {}
+                        if cursor_requests {
+                            decoder.expect_cursor_position();
+                        }
+                    }
+                    Action::ExecuteSilent(command) => {
+                        screen_sender
+                            .send(Event::ExecuteSilent(command.clone()))
+                            .await?;
+                    }
+                    Action::Reload(command) => {
+                        screen_sender.send(Event::Reload(command.clone())).await?;
+                    }
+                    Action::Append(command) => {
+                        screen_sender.send(Event::Append(command.clone())).await?;
+                    }
+                    Action::ClearList => {
+                        screen_sender.send(Event::ClearList).await?;
+                    }
+                    Action::NextMode if prompts.len() > 1 => {
+                        prompts[mode] = prompt.clone();
+                        mode = (mode + 1) % prompts.len();
+                        prompt = prompts[mode].clone();
+                        prompt.refresh();
+                        operator = None;
+
+                        screen_sender.send(Event::SwitchMode(mode)).await?;
+                        engine_sender.send(Event::SwitchMode(mode)).await?;
+                        query_updated = true;
+                    }
+                    Action::NextMode => (),
+                    Action::ToggleSort => {
+                        engine_sender.send(Event::ToggleSort).await?;
+                    }
+                    Action::NextAlgo => {
+                        algo = algo.next();
+
+                        screen_sender.send(Event::SwitchAlgo(algo)).await?;
+                        engine_sender.send(Event::SwitchAlgo(algo)).await?;
+                        query_updated = true;
+                    }
+                    Action::ToggleMark => {
+                        screen_sender.send(Event::ToggleMark).await?;
+                    }
+                }
+
+                continue;
+            }
+
+            let key = if vi_mode {
+                match vi::handle(key, &mut prompt, &mut operator) {
+                    vi::Action::Key(key) => key,
+                    vi::Action::Edit => {
+                        query_updated = true;
+                        continue;
+                    }
+                    vi::Action::Move => {
+                        screen_sender.send(Event::Search((&prompt).into())).await?;
+                        continue;
+                    }
+                    vi::Action::Ignore => continue,
+                }
+            } else {
+                key
+            };
 
-        for key in keys {
             match key {
                 Key::Ctrl('p') | Key::Up => {
                     screen_sender.send(Event::Up).await?;
@@ -75,6 +268,24 @@ where
                 Key::Ctrl('n') | Key::Down => {
                     screen_sender.send(Event::Down).await?;
                 }
+                Key::PageUp => {
+                    screen_sender.send(Event::PageUp).await?;
+                }
+                Key::PageDown => {
+                    screen_sender.send(Event::PageDown).await?;
+                }
+                Key::Alt('p') => {
+                    screen_sender.send(Event::HalfPageUp).await?;
+                }
+                Key::Alt('n') => {
+                    screen_sender.send(Event::HalfPageDown).await?;
+                }
+                Key::Alt('<') => {
+                    screen_sender.send(Event::First).await?;
+                }
+                Key::Alt('>') => {
+                    screen_sender.send(Event::Last).await?;
+                }
 
                 Key::Esc | Key::Alt('\u{0}') => {
                     screen_sender.send(Event::Exit).await?;
@@ -94,7 +305,32 @@ where
                     query_updated = true;
                 }
                 Key::Backspace => {
-                    query_updated = prompt.backspace();
+                    query_updated |= prompt.backspace();
+                }
+                Key::Delete => {
+                    query_updated |= prompt.delete();
+                }
+                Key::Ctrl('w') => {
+                    query_updated |= prompt.delete_word_backward();
+                }
+                Key::Alt('d') => {
+                    query_updated |= prompt.delete_word_forward();
+                }
+                Key::Ctrl('k') => {
+                    query_updated |= prompt.kill_to_end();
+                }
+                Key::Ctrl('y') => {
+                    query_updated |= prompt.yank();
+                }
+                Key::Ctrl('t') => {
+                    query_updated |= prompt.transpose();
+                }
+                // `<C-_>` and `<C-/>` are both received as the same control character
+                Key::Ctrl('7') => {
+                    query_updated |= prompt.undo();
+                }
+                Key::Alt('/') => {
+                    query_updated |= prompt.redo();
                 }
                 Key::Char(ch) => {
                     prompt.add(ch);
@@ -103,19 +339,27 @@ where
 
                 Key::Left => {
                     prompt.left();
-                    screen_sender.send(Event::Search(prompt.clone())).await?;
+                    screen_sender.send(Event::Search((&prompt).into())).await?;
                 }
                 Key::Right => {
                     prompt.right();
-                    screen_sender.send(Event::Search(prompt.clone())).await?;
+                    screen_sender.send(Event::Search((&prompt).into())).await?;
                 }
                 Key::Ctrl('a') => {
                     prompt.cursor_at_start();
-                    screen_sender.send(Event::Search(prompt.clone())).await?;
+                    screen_sender.send(Event::Search((&prompt).into())).await?;
                 }
                 Key::Ctrl('e') => {
                     prompt.cursor_at_end();
-                    screen_sender.send(Event::Search(prompt.clone())).await?;
+                    screen_sender.send(Event::Search((&prompt).into())).await?;
+                }
+                Key::Alt('b') => {
+                    prompt.word_left();
+                    screen_sender.send(Event::Search((&prompt).into())).await?;
+                }
+                Key::Alt('f') => {
+                    prompt.word_right();
+                    screen_sender.send(Event::Search((&prompt).into())).await?;
                 }
 
                 _ => (),
@@ -123,8 +367,8 @@ where
         }
 
         if query_updated {
-            screen_sender.send(Event::Search(prompt.clone())).await?;
-            engine_sender.send(Event::Search(prompt.clone())).await?;
+            screen_sender.send(Event::Search((&prompt).into())).await?;
+            engine_sender.send(Event::Search((&prompt).into())).await?;
         }
     }
 
@@ -133,8 +377,27 @@ where
     Ok(())
 }
 
-fn keys(buffer: &mut [u8], num: usize) -> Vec<Key> {
-    let tmp: Vec<u8> = buffer.iter().take(num).copied().collect();
+fn mouse_event(mouse: MouseEvent, last_click: &mut Option<(Instant, u16)>) -> Option<Event> {
+    match mouse {
+        MouseEvent::Press(MouseButton::WheelUp, _, _) => Some(Event::Up),
+        MouseEvent::Press(MouseButton::WheelDown, _, _) => Some(Event::Down),
+        MouseEvent::Press(MouseButton::Left, _, line) => {
+            let now = Instant::now();
+            let double = match last_click {
+                Some((at, last_line)) => {
+                    *last_line == line && now.duration_since(*at) <= DOUBLE_CLICK_INTERVAL
+                }
+                None => false,
+            };
 
-    tmp.keys().filter_map(|k| k.ok()).collect()
+            if double {
+                *last_click = None;
+                Some(Event::DoubleClick(line))
+            } else {
+                *last_click = Some((now, line));
+                Some(Event::Click(line))
+            }
+        }
+        _ => None,
+    }
 }
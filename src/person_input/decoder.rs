@@ -0,0 +1,433 @@
+//! Decode the bytes read from the PTTY into keys, mouse events and the rest of inputs
+//!
+//! The bytes arrive in chunks of any size, so a sequence (an escape sequence, a mouse report or a
+//! multi-byte UTF-8 character) can be split between two reads. The decoder keeps any incomplete
+//! sequence until the rest of it arrives.
+//!
+//! A lone escape byte can be either the `Esc` key or the start of a sequence still on its way.
+//! When no more bytes arrive after a while the pending bytes are flushed and the escape is
+//! taken as the `Esc` key. An escape followed by a single character, like the `ESC O` or
+//! `ESC [` that `Alt-O` and `Alt-[` send, is taken as that character with `Alt`.
+//!
+//! Keys with modifiers that have no `Key` of their own, like `Ctrl-Up` (`ESC [ 1 ; 5 A`) or
+//! `Shift-F3`, are read whole and reported as unsupported.
+//!
+//! The answer to a cursor position request (`ESC [ line ; column R`) looks like some of those
+//! keys (`ESC [ 1 ; 5 R` is `Ctrl-F3` in xterm), so it's only taken as a cursor position while
+//! a request is waiting for its answer.
+use termion::event::{parse_event, Event as TermEvent, Key, MouseButton, MouseEvent};
+
+const ESC: u8 = 0x1b;
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Everything the person can send through the PTTY
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Term(TermEvent),
+    /// Answer to a cursor position request, with the line of the cursor
+    CursorPosition(u16),
+    /// Text pasted in the terminal
+    Paste(String),
+}
+
+#[derive(Debug, Default)]
+pub struct Decoder {
+    pending: Vec<u8>,
+    paste: Option<Vec<u8>>,
+    cursor_requested: bool,
+}
+
+impl Decoder {
+    /// Decode the given bytes, keeping any incomplete sequence for the next call
+    pub fn decode(&mut self, bytes: &[u8]) -> Vec<Input> {
+        self.pending.extend_from_slice(bytes);
+
+        self.decode_pending()
+    }
+
+    /// Take the next cursor position report as the answer to a request sent to the terminal
+    pub fn expect_cursor_position(&mut self) {
+        self.cursor_requested = true;
+    }
+
+    /// Whether there is an incomplete sequence waiting for more bytes
+    pub fn is_waiting(&self) -> bool {
+        self.paste.is_none() && !self.pending.is_empty()
+    }
+
+    /// Decode the incomplete sequence as it is, when the rest of it didn't arrive on time.
+    ///
+    /// An escape byte followed by a single character is that character with `Alt`. Any other
+    /// escape byte is taken as the `Esc` key and the bytes after it are decoded on their own
+    pub fn flush(&mut self) -> Vec<Input> {
+        let mut inputs = vec![];
+
+        while self.is_waiting() {
+            if let [ESC, byte] = self.pending[..] {
+                if byte.is_ascii() && !byte.is_ascii_control() {
+                    inputs.push(Input::Term(TermEvent::Key(Key::Alt(byte as char))));
+                    self.pending.clear();
+                    break;
+                }
+            }
+
+            let byte = self.pending.remove(0);
+            if byte == ESC {
+                inputs.push(Input::Term(TermEvent::Key(Key::Esc)));
+            } else {
+                log::trace!("dropping incomplete input {:?}", byte);
+            }
+
+            inputs.append(&mut self.decode_pending());
+        }
+
+        inputs
+    }
+
+    fn decode_pending(&mut self) -> Vec<Input> {
+        let bytes = std::mem::take(&mut self.pending);
+        let mut inputs = vec![];
+        let mut idx = 0;
+
+        while idx < bytes.len() {
+            let rest = &bytes[idx..];
+
+            if let Some(paste) = &mut self.paste {
+                match find(rest, PASTE_END) {
+                    Some(end) => {
+                        paste.extend_from_slice(&rest[..end]);
+                        inputs.push(Input::Paste(String::from_utf8_lossy(paste).into_owned()));
+                        self.paste = None;
+                        idx += end + PASTE_END.len();
+                    }
+                    None => {
+                        // The end of the paste could be split between reads
+                        let keep = partial_suffix(rest, PASTE_END);
+                        paste.extend_from_slice(&rest[..rest.len() - keep]);
+                        idx = bytes.len() - keep;
+                        break;
+                    }
+                }
+
+                continue;
+            }
+
+            let len = match sequence_len(rest) {
+                Some(len) => len,
+                None => break,
+            };
+
+            let sequence = &rest[..len];
+            if sequence == PASTE_START {
+                self.paste = Some(vec![]);
+            } else {
+                let input = parse(sequence, self.cursor_requested);
+                if let Input::CursorPosition(_) = input {
+                    self.cursor_requested = false;
+                }
+
+                inputs.push(input);
+            }
+
+            idx += len;
+        }
+
+        self.pending = bytes[idx..].to_vec();
+
+        inputs
+    }
+}
+
+// Length of the sequence at the start of the bytes or `None` if it's incomplete
+fn sequence_len(bytes: &[u8]) -> Option<usize> {
+    match bytes {
+        [] | [ESC] => None,
+        [ESC, b'[', rest @ ..] => csi_len(rest).map(|len| len + 2),
+        [ESC, b'O', rest @ ..] => rest.first().map(|_| 3),
+        [ESC, rest @ ..] => utf8_len(rest).map(|len| len + 1),
+        _ => utf8_len(bytes),
+    }
+}
+
+// Length of a control sequence after the `ESC [` introducer
+fn csi_len(bytes: &[u8]) -> Option<usize> {
+    match bytes {
+        [] => None,
+        // X10 mouse reports have three bytes after the `M`
+        [b'M', ..] => Some(4).filter(|len| bytes.len() >= *len),
+        // Linux console function keys
+        [b'[', ..] => Some(2).filter(|len| bytes.len() >= *len),
+        // Everything until the final byte
+        _ => bytes
+            .iter()
+            .position(|b| (0x40..=0x7e).contains(b))
+            .map(|idx| idx + 1),
+    }
+}
+
+fn utf8_len(bytes: &[u8]) -> Option<usize> {
+    let len = match bytes.first()? {
+        0xf0..=0xff => 4,
+        0xe0..=0xef => 3,
+        0xc0..=0xdf => 2,
+        _ => 1,
+    };
+
+    Some(len).filter(|len| bytes.len() >= *len)
+}
+
+fn parse(sequence: &[u8], cursor_requested: bool) -> Input {
+    match sequence {
+        [ESC, b'[', b'<', rest @ ..] => sgr_mouse(rest),
+        [ESC, b'[', rest @ ..] if !matches!(rest.first(), Some(b'M') | Some(b'[')) => {
+            csi(rest, cursor_requested)
+        }
+        _ => {
+            let mut iter = sequence[1..].iter().map(|b| Ok(*b));
+
+            match parse_event(sequence[0], &mut iter) {
+                Ok(event) => Input::Term(event),
+                Err(_) => unsupported(sequence),
+            }
+        }
+    }
+}
+
+// Parse a control sequence after the `ESC [` introducer, like `ESC [ 5 ~` for `PageUp`
+fn csi(bytes: &[u8], cursor_requested: bool) -> Input {
+    let (last, params) = match bytes.split_last() {
+        Some(split) => split,
+        None => return unsupported(bytes),
+    };
+    let params = match numbers(params) {
+        Some(params) => params,
+        None => return unsupported(bytes),
+    };
+
+    let key = match (last, params.as_slice()) {
+        (b'R', [line, _column]) if cursor_requested => return Input::CursorPosition(*line as u16),
+
+        (b'A', []) => Key::Up,
+        (b'B', []) => Key::Down,
+        (b'C', []) => Key::Right,
+        (b'D', []) => Key::Left,
+        (b'H', []) => Key::Home,
+        (b'F', []) => Key::End,
+        (b'Z', []) => Key::BackTab,
+
+        (b'~', [code]) => match code {
+            1 | 7 => Key::Home,
+            2 => Key::Insert,
+            3 => Key::Delete,
+            4 | 8 => Key::End,
+            5 => Key::PageUp,
+            6 => Key::PageDown,
+            11..=15 => Key::F((code - 10) as u8),
+            17..=21 => Key::F((code - 11) as u8),
+            23..=24 => Key::F((code - 12) as u8),
+            _ => return unsupported(bytes),
+        },
+
+        // Keys reported with the kitty keyboard protocol: `ESC [ code ; modifiers u`
+        (b'u', [code]) => match kitty_key(*code, 0) {
+            Some(key) => key,
+            None => return unsupported(bytes),
+        },
+        (b'u', [code, modifiers]) => match kitty_key(*code, modifiers.saturating_sub(1)) {
+            Some(key) => key,
+            None => return unsupported(bytes),
+        },
+
+        _ => return unsupported(bytes),
+    };
+
+    Input::Term(TermEvent::Key(key))
+}
+
+fn kitty_key(code: u32, modifiers: u32) -> Option<Key> {
+    const SHIFT: u32 = 0b1;
+    const ALT: u32 = 0b10;
+    const CTRL: u32 = 0b100;
+
+    let ch = match code {
+        9 => '\t',
+        13 => '\n',
+        27 => return Some(Key::Esc).filter(|_| modifiers == 0),
+        127 => return Some(Key::Backspace).filter(|_| modifiers == 0),
+        code => std::char::from_u32(code)?,
+    };
+
+    match modifiers & !SHIFT {
+        0 => Some(Key::Char(ch)),
+        ALT => Some(Key::Alt(ch)),
+        CTRL => Some(Key::Ctrl(ch)),
+        _ => None,
+    }
+}
+
+// Parse a mouse report in the SGR format: `ESC [ < button ; column ; line (M or m)`
+fn sgr_mouse(bytes: &[u8]) -> Input {
+    let event = bytes.split_last().and_then(|(last, params)| {
+        let (button, column, line) = match numbers(params)?.as_slice() {
+            [button, column, line] => (*button, *column as u16, *line as u16),
+            _ => return None,
+        };
+
+        let event = match (button, last) {
+            (0, b'M') => MouseEvent::Press(MouseButton::Left, column, line),
+            (1, b'M') => MouseEvent::Press(MouseButton::Middle, column, line),
+            (2, b'M') => MouseEvent::Press(MouseButton::Right, column, line),
+            (64, b'M') => MouseEvent::Press(MouseButton::WheelUp, column, line),
+            (65, b'M') => MouseEvent::Press(MouseButton::WheelDown, column, line),
+            (32, b'M') => MouseEvent::Hold(column, line),
+            (_, b'm') => MouseEvent::Release(column, line),
+            _ => return None,
+        };
+
+        Some(event)
+    });
+
+    match event {
+        Some(event) => Input::Term(TermEvent::Mouse(event)),
+        None => unsupported(bytes),
+    }
+}
+
+// Semicolon separated numbers, like the parameters of a control sequence
+fn numbers(bytes: &[u8]) -> Option<Vec<u32>> {
+    if bytes.is_empty() {
+        return Some(vec![]);
+    }
+
+    std::str::from_utf8(bytes)
+        .ok()?
+        .split(';')
+        .map(|n| n.parse::<u32>().ok())
+        .collect()
+}
+
+fn unsupported(bytes: &[u8]) -> Input {
+    Input::Term(TermEvent::Unsupported(bytes.to_vec()))
+}
+
+fn find(bytes: &[u8], needle: &[u8]) -> Option<usize> {
+    bytes
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+// Length of the longest end of the bytes that is the start of the needle
+fn partial_suffix(bytes: &[u8], needle: &[u8]) -> usize {
+    (1..needle.len())
+        .rev()
+        .find(|len| bytes.ends_with(&needle[..*len]))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key) -> Input {
+        Input::Term(TermEvent::Key(key))
+    }
+
+    #[test]
+    fn sequences_split_between_reads_test() {
+        let mut decoder = Decoder::default();
+
+        assert_eq!(decoder.decode(b"a\x1b"), vec![key(Key::Char('a'))]);
+        assert!(decoder.is_waiting());
+        assert_eq!(decoder.decode(b"[5"), vec![]);
+        assert_eq!(decoder.decode(b"~\xc3"), vec![key(Key::PageUp)]);
+        assert_eq!(decoder.decode(b"\xb1"), vec![key(Key::Char('ñ'))]);
+        assert!(!decoder.is_waiting());
+
+        assert_eq!(decoder.decode(b"\x1b[<0;3"), vec![]);
+        assert_eq!(
+            decoder.decode(b";4M"),
+            vec![Input::Term(TermEvent::Mouse(MouseEvent::Press(
+                MouseButton::Left,
+                3,
+                4
+            )))]
+        );
+    }
+
+    #[test]
+    fn escape_and_alt_keys_test() {
+        let mut decoder = Decoder::default();
+
+        assert_eq!(decoder.decode(b"\x1bf"), vec![key(Key::Alt('f'))]);
+
+        assert_eq!(decoder.decode(b"\x1b"), vec![]);
+        assert_eq!(decoder.flush(), vec![key(Key::Esc)]);
+        assert!(!decoder.is_waiting());
+
+        // `Alt-[` and `Alt-O` look like the start of a sequence until nothing else arrives
+        assert_eq!(decoder.decode(b"\x1b["), vec![]);
+        assert_eq!(decoder.flush(), vec![key(Key::Alt('['))]);
+        assert_eq!(decoder.decode(b"\x1bO"), vec![]);
+        assert_eq!(decoder.flush(), vec![key(Key::Alt('O'))]);
+        assert!(!decoder.is_waiting());
+
+        assert_eq!(decoder.decode(b"\x1b[1"), vec![]);
+        assert_eq!(
+            decoder.flush(),
+            vec![key(Key::Esc), key(Key::Char('[')), key(Key::Char('1'))]
+        );
+    }
+
+    #[test]
+    fn control_sequences_test() {
+        let mut decoder = Decoder::default();
+
+        decoder.expect_cursor_position();
+        assert_eq!(
+            decoder.decode(b"\x1b[A\x1b[3~\x1bOP\x1b[12;40R"),
+            vec![
+                key(Key::Up),
+                key(Key::Delete),
+                key(Key::F(1)),
+                Input::CursorPosition(12)
+            ]
+        );
+
+        // without a request waiting for its answer it's `Ctrl-F3`, which can't be bound
+        assert_eq!(decoder.decode(b"\x1b[1;5R"), vec![unsupported(b"1;5R")]);
+
+        // unknown sequences don't break the next ones
+        assert_eq!(
+            decoder.decode(b"\x1b[1;5A\x1b[15;2~\x1b[2004~x"),
+            vec![
+                unsupported(b"1;5A"),
+                unsupported(b"15;2~"),
+                unsupported(b"2004~"),
+                key(Key::Char('x'))
+            ]
+        );
+
+        // kitty keyboard protocol
+        assert_eq!(
+            decoder.decode(b"\x1b[97;5u\x1b[98;3u\x1b[27u"),
+            vec![key(Key::Ctrl('a')), key(Key::Alt('b')), key(Key::Esc)]
+        );
+    }
+
+    #[test]
+    fn paste_split_between_reads_test() {
+        let mut decoder = Decoder::default();
+
+        assert_eq!(
+            decoder.decode(b"a\x1b[200~foo\nb"),
+            vec![key(Key::Char('a'))]
+        );
+        assert!(!decoder.is_waiting());
+        assert_eq!(decoder.decode(b"ar\x1b[20"), vec![]);
+        assert_eq!(
+            decoder.decode(b"1~b"),
+            vec![Input::Paste("foo\nbar".into()), key(Key::Char('b'))]
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Vi editing mode for the prompt
+//!
+//! In insert mode the keys work as usual, except for `Esc` that goes to normal mode. In normal mode
+//! the keys are commands:
+//!
+//! * `h`, `l`, `w`, `b`, `e`, `0` and `$` move the cursor
+//! * `x` removes the character under the cursor, `D` everything until the end of the prompt
+//! * `dw` removes until the start of the next word and `dd` the whole query
+//! * `cw`, `cc` and `C` work like `dw`, `dd` and `D` but go to insert mode afterwards
+//! * `i`, `a`, `I` and `A` go to insert mode
+//! * `u` undoes the last change and `<C-r>` redoes it
+//! * `j` and `k` move the selection down and up the list
+//! * `Esc` exits the program
+use crate::common::{Prompt, PromptMode};
+use termion::event::Key;
+
+/// What to do after a key is handled by the vi mode
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    /// Handle the given key as in the default editing mode
+    Key(Key),
+    /// The query was updated
+    Edit,
+    /// Only the cursor or the mode were updated
+    Move,
+    /// Nothing to do
+    Ignore,
+}
+
+/// Handle a key in the vi editing mode.
+///
+/// The operator keeps a `d` or `c` waiting for its motion between calls
+pub fn handle(key: Key, prompt: &mut Prompt, operator: &mut Option<char>) -> Action {
+    match prompt.mode() {
+        PromptMode::Insert => insert_mode(key, prompt),
+        PromptMode::Normal => {
+            let action = match operator.take() {
+                Some(op) => pending_operator(op, key, prompt),
+                None => normal_mode(key, prompt, operator),
+            };
+
+            if prompt.mode() == PromptMode::Normal {
+                keep_cursor_in_query(prompt);
+            }
+
+            action
+        }
+    }
+}
+
+fn insert_mode(key: Key, prompt: &mut Prompt) -> Action {
+    match key {
+        Key::Esc => {
+            prompt.set_mode(PromptMode::Normal);
+            prompt.left();
+
+            Action::Move
+        }
+        key => Action::Key(key),
+    }
+}
+
+fn normal_mode(key: Key, prompt: &mut Prompt, operator: &mut Option<char>) -> Action {
+    let ch = match key {
+        Key::Char('\n') => return Action::Key(key),
+        Key::Char(ch) => ch,
+        Key::Ctrl('r') => return edit(prompt.redo()),
+        key => return Action::Key(key),
+    };
+
+    match ch {
+        'j' => Action::Key(Key::Down),
+        'k' => Action::Key(Key::Up),
+
+        'h' => {
+            prompt.left();
+            Action::Move
+        }
+        'l' => {
+            if prompt.cursor_until_end() > 1 {
+                prompt.right();
+            }
+            Action::Move
+        }
+        'w' => {
+            prompt.word_start_right();
+            Action::Move
+        }
+        'b' => {
+            prompt.word_left();
+            Action::Move
+        }
+        'e' => {
+            prompt.word_end_right();
+            Action::Move
+        }
+        '0' => {
+            prompt.cursor_at_start();
+            Action::Move
+        }
+        '$' => {
+            prompt.cursor_at_end();
+            Action::Move
+        }
+
+        'x' => edit(prompt.delete()),
+        'D' => edit(prompt.kill_to_end()),
+        'C' => {
+            let updated = prompt.kill_to_end();
+            insert(prompt, updated)
+        }
+        'u' => edit(prompt.undo()),
+        'd' | 'c' => {
+            *operator = Some(ch);
+            Action::Ignore
+        }
+
+        'i' => insert(prompt, false),
+        'a' => {
+            prompt.right();
+            insert(prompt, false)
+        }
+        'I' => {
+            prompt.cursor_at_start();
+            insert(prompt, false)
+        }
+        'A' => {
+            prompt.cursor_at_end();
+            insert(prompt, false)
+        }
+
+        _ => Action::Ignore,
+    }
+}
+
+fn pending_operator(operator: char, key: Key, prompt: &mut Prompt) -> Action {
+    let updated = match (operator, key) {
+        ('d', Key::Char('w')) => prompt.delete_to_word_start(),
+        // Like in vi, `cw` doesn't remove the spaces after the word
+        ('c', Key::Char('w')) => prompt.delete_word_forward(),
+        ('d', Key::Char('d')) | ('c', Key::Char('c')) => {
+            let updated = !prompt.is_empty();
+            prompt.clear();
+            updated
+        }
+        _ => return Action::Ignore,
+    };
+
+    if operator == 'c' {
+        insert(prompt, updated)
+    } else {
+        edit(updated)
+    }
+}
+
+fn insert(prompt: &mut Prompt, updated: bool) -> Action {
+    prompt.set_mode(PromptMode::Insert);
+
+    if updated {
+        Action::Edit
+    } else {
+        Action::Move
+    }
+}
+
+fn edit(updated: bool) -> Action {
+    if updated {
+        Action::Edit
+    } else {
+        Action::Ignore
+    }
+}
+
+// In normal mode the cursor is always on top of a character
+fn keep_cursor_in_query(prompt: &mut Prompt) {
+    if !prompt.is_empty() && prompt.cursor_until_end() == 0 {
+        prompt.left();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(prompt: &mut Prompt, keys: &str) -> Vec<Action> {
+        let mut operator = None;
+
+        keys.chars()
+            .map(|ch| {
+                let key = if ch == '\u{1b}' {
+                    Key::Esc
+                } else {
+                    Key::Char(ch)
+                };
+
+                let action = handle(key, prompt, &mut operator);
+                // characters typed in insert mode go to the query as usual
+                if let Action::Key(Key::Char(ch)) = action {
+                    if prompt.mode() == PromptMode::Insert {
+                        prompt.add(ch);
+                    }
+                }
+
+                action
+            })
+            .collect()
+    }
+
+    fn cursor(prompt: &Prompt) -> usize {
+        prompt.len() - prompt.cursor_until_end()
+    }
+
+    #[test]
+    fn normal_mode_motions_test() {
+        let mut prompt: Prompt = (&"foo bar-baz".to_string()).into();
+
+        keys(&mut prompt, "\u{1b}");
+        assert_eq!(prompt.mode(), PromptMode::Normal);
+        assert_eq!(cursor(&prompt), 10);
+
+        keys(&mut prompt, "$l");
+        assert_eq!(cursor(&prompt), 10);
+
+        keys(&mut prompt, "0w");
+        assert_eq!(cursor(&prompt), 4);
+        keys(&mut prompt, "e");
+        assert_eq!(cursor(&prompt), 6);
+        keys(&mut prompt, "bh");
+        assert_eq!(cursor(&prompt), 3);
+    }
+
+    #[test]
+    fn normal_mode_edits_test() {
+        let mut prompt: Prompt = (&"foo bar-baz".to_string()).into();
+
+        assert_eq!(
+            keys(&mut prompt, "\u{1b}0dw"),
+            vec![Action::Move, Action::Move, Action::Ignore, Action::Edit]
+        );
+        assert_eq!(prompt.as_string(), "bar-baz");
+
+        keys(&mut prompt, "x$");
+        assert_eq!(prompt.as_string(), "ar-baz");
+        assert_eq!(cursor(&prompt), 5);
+
+        keys(&mut prompt, "x");
+        assert_eq!(prompt.as_string(), "ar-ba");
+        assert_eq!(cursor(&prompt), 4);
+
+        keys(&mut prompt, "u");
+        assert_eq!(prompt.as_string(), "ar-baz");
+
+        keys(&mut prompt, "0cwfoo");
+        assert_eq!(prompt.mode(), PromptMode::Insert);
+        assert_eq!(prompt.as_string(), "foo-baz");
+
+        keys(&mut prompt, "\u{1b}dd");
+        assert!(prompt.is_empty());
+    }
+
+    #[test]
+    fn insert_mode_keys_test() {
+        let mut prompt: Prompt = (&"bar".to_string()).into();
+
+        keys(&mut prompt, "\u{1b}Ifoo ");
+        assert_eq!(prompt.as_string(), "foo bar");
+
+        keys(&mut prompt, "\u{1b}A!");
+        assert_eq!(prompt.as_string(), "foo bar!");
+
+        assert_eq!(
+            keys(&mut prompt, "\u{1b}jk"),
+            vec![Action::Move, Action::Key(Key::Down), Action::Key(Key::Up)]
+        );
+    }
+}
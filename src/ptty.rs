@@ -4,8 +4,14 @@ use crate::common::Result;
 use async_std::fs;
 use async_std::os::unix::io::RawFd;
 use std::convert::TryFrom;
+use std::sync::Mutex;
 use termios::{self, Termios};
 
+lazy_static! {
+    // Settings of the terminal before the program changed them
+    static ref ORIGINAL: Mutex<Option<Termios>> = Mutex::new(None);
+}
+
 #[derive(Debug)]
 pub struct PTTY {
     fd: RawFd,
@@ -26,6 +32,11 @@ impl PTTY {
     ///
     /// [termios]: https://linux.die.net/man/3/termios
     pub fn noncanonical_mode(&self) -> Result<()> {
+        ORIGINAL
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get_or_insert(self.termios);
+
         let mut raw_tty = self.termios;
         raw_tty.c_lflag &= !(termios::ICANON | termios::ECHO | termios::ECHONL | termios::IEXTEN);
 
@@ -33,6 +44,28 @@ impl PTTY {
 
         Ok(())
     }
+
+    /// Undo the noncanonical mode so other programs can use the PTTY like in a shell, with line
+    /// editing and echo. The terminal goes back to the settings it had before the program
+    /// changed them.
+    ///
+    /// The previous settings are restored when the PTTY is dropped
+    pub fn canonical_mode(&self) -> Result<()> {
+        let original = *ORIGINAL.lock().map_err(|e| e.to_string())?;
+        let tty = match original {
+            Some(tty) => tty,
+            // The terminal was never changed, just make sure it's in canonical mode
+            None => {
+                let mut tty = self.termios;
+                tty.c_lflag |= termios::ICANON | termios::ECHO | termios::IEXTEN;
+                tty
+            }
+        };
+
+        termios::tcsetattr(self.fd, termios::TCSANOW, &tty)?;
+
+        Ok(())
+    }
 }
 
 impl TryFrom<RawFd> for PTTY {
@@ -5,19 +5,42 @@
 //! moving, typing, etc.
 //!
 //! When the program finishes this is the task that will return the final person's selection.
+//!
+//! Commands that feed lines to the search engine (`reload(CMD)` and `append(CMD)`) also start
+//! here, since their placeholders depend on the selection state. Starting a new list cancels the
+//! commands that are still running, so their lines don't end up mixed with the new ones.
+//!
+//! The same goes for the commands of each mode, which all start with the program. Every mode has
+//! its own pool in the engine, so its commands are tracked separately.
+//!
+//! While the input is loading the gauge shows a spinner, so the screen is drawn again every now
+//! and then even if nothing else happens.
+//!
+//! The engine only sends the best matches of a search, and only those can be selected. When the
+//! selection gets close to the last one, this task asks the engine for more of them.
 
+use crate::command;
 use crate::common::{Result, Text};
 use crate::config::Config;
+use crate::data_input;
 use crate::events::Event;
 use crate::state::State;
-use crate::ui::Canvas;
-use async_std::channel::Receiver;
+use crate::ui::{Canvas, SPINNER_INTERVAL};
+use async_std::channel::{Receiver, Sender};
+use async_std::future;
 use async_std::io;
 use async_std::prelude::*;
+use async_std::task::{self, JoinHandle};
 use std::time::Instant;
 
 /// Run the screen's task
-pub async fn task<W>(config: Config, outbound: W, mut recv: Receiver<Event>) -> Result<Option<Text>>
+pub async fn task<W>(
+    config: Config,
+    outbound: W,
+    mut recv: Receiver<Event>,
+    person_sender: Sender<Event>,
+    engine_sender: Sender<Event>,
+) -> Result<Option<Text>>
 where
     W: io::Write + Send + Unpin + 'static,
 {
@@ -26,57 +49,210 @@ where
     let mut last_timestamp = Instant::now();
     let mut render: bool;
     let mut selection = None;
+    let mut active = 0;
+    // Whether more matches have been requested and they haven't arrived yet
+    let mut loading = false;
+    // Running commands for each pool
+    let mut sources: Vec<Vec<JoinHandle<Result<()>>>> =
+        (0..config.modes.len().max(1)).map(|_| vec![]).collect();
+
+    let reverse = config.screen.layout().is_reverse();
 
     let mut state = State::new();
+    state.set_cycle(!config.no_cycle);
     let mut canvas = Canvas::new(&config, outbound).await?;
 
+    for (idx, mode) in config.modes.iter().enumerate() {
+        let source = data_input::command_task(mode.command.clone(), idx, engine_sender.clone());
+        sources[idx].push(task::spawn(source));
+    }
+    state.set_mode(config.modes.first().map(|mode| mode.name.clone()));
+    state.set_algo(config.algo);
+
     canvas.render(&state).await?;
 
-    while let Some(event) = recv.next().await {
+    loop {
+        let event = if state.loading_since().is_some() {
+            // Nothing else happened, just move the spinner
+            match future::timeout(SPINNER_INTERVAL, recv.next()).await {
+                Ok(event) => event,
+                Err(_) => Some(Event::Ignore),
+            }
+        } else {
+            recv.next().await
+        };
+
+        let event = match event {
+            Some(event) => event,
+            None => break,
+        };
         render = false;
 
         match event {
-            Event::Search(prompt) => {
-                log::trace!("printing prompt: {:?}", prompt);
+            // Draw the next frame of the spinner
+            Event::Ignore if state.loading_since().is_some() => {
+                state.set_loading(true);
+                render = true;
+            }
+            Event::Loading(loading) => {
+                state.set_loading(loading);
+                render = true;
+            }
+            Event::Truncated(truncated) => {
+                state.set_truncated(truncated);
+                render = true;
+            }
+
+            Event::Search(search) => {
+                log::trace!("printing prompt: {:?}", search);
 
-                last_timestamp = prompt.timestamp();
-                state.set_search(prompt);
+                last_timestamp = search.timestamp();
+                state.set_search(search);
                 render = true;
             }
 
-            Event::Flush((matches, len)) => {
+            Event::Flush((matches, len, matcher)) => {
                 log::trace!("flushing matches");
 
                 // Flush happens when the pool size
                 // changes or the pool is complete
                 state.set_matches((matches, len));
+                state.set_matcher(matcher);
+                loading = false;
                 render = true;
             }
 
-            Event::SearchDone((matches, len, timestamp)) => {
-                // Only if the search timestamp is the same as the last query timestamp
-                // we will update the state. This way we will drop any intermediate search
-                // and reduce the number of renders
-                if timestamp >= last_timestamp {
-                    log::trace!("printing new search results");
+            // Only if the search timestamp is the same as the last query timestamp
+            // we will update the state. This way we will drop any intermediate search
+            // and reduce the number of renders
+            Event::SearchDone((matches, len, matcher, timestamp))
+                if timestamp >= last_timestamp =>
+            {
+                log::trace!("printing new search results");
 
-                    state.set_matches((matches, len));
+                state.set_search_time(timestamp.elapsed());
+                state.set_matches((matches, len));
+                state.set_matcher(matcher);
+                loading = false;
+                render = true;
+            }
+
+            Event::Header(header) => {
+                log::trace!("printing header");
+
+                state.set_header(header);
+                render = true;
+            }
+
+            Event::Up
+            | Event::Down
+            | Event::PageUp
+            | Event::PageDown
+            | Event::HalfPageUp
+            | Event::HalfPageDown
+            | Event::First
+            | Event::Last => {
+                log::trace!("moving selection: {:?}", event);
+
+                navigate(&mut state, &event, canvas.page_size(), reverse);
+                render = true;
+
+                let loaded = state.matches().len();
+                let needed = state.selection_idx() + canvas.page_size() + 1;
+                if !loading && loaded < state.matches_count() && needed > loaded {
+                    log::trace!("loading more matches");
+
+                    loading = true;
+                    engine_sender
+                        .send(Event::LoadMore((loaded * 2).max(needed)))
+                        .await?;
+                }
+            }
+
+            Event::ToggleMark => {
+                state.toggle_mark();
+                render = true;
+            }
+
+            Event::CursorPosition(line) => {
+                canvas.set_cursor_position(line);
+            }
+            Event::Click(line) => {
+                if let Some(idx) = canvas.candidate_at(line, &state) {
+                    log::trace!("selecting candidate {} with the mouse", idx);
+
+                    state.select(idx);
                     render = true;
                 }
             }
+            Event::DoubleClick(line) => {
+                if let Some(idx) = canvas.candidate_at(line, &state) {
+                    state.select(idx);
+                    selection = state.selection();
+                    break;
+                }
+            }
+
+            Event::Execute(template) => {
+                let command = command::expand(&template, &state);
+
+                canvas.suspend().await?;
+                if let Err(e) = command::execute(&command).await {
+                    log::error!("error executing {:?}: {}", command, e);
+                }
+                canvas.resume(&state).await?;
+
+                person_sender.send(Event::Resume).await?;
+            }
+            Event::ExecuteSilent(template) => {
+                let command = command::expand(&template, &state);
+
+                if let Err(e) = command::execute_silent(&command) {
+                    log::error!("error executing {:?}: {}", command, e);
+                }
+            }
+
+            Event::Reload(template) => {
+                let command = command::expand(&template, &state);
+                log::trace!("reloading the list with {:?}", command);
+
+                clear_list(&mut sources[active], active, &engine_sender).await?;
+                state.clear_matches();
+                render = true;
+
+                let source = data_input::command_task(command, active, engine_sender.clone());
+                sources[active].push(task::spawn(source));
+            }
+            Event::Append(template) => {
+                let command = command::expand(&template, &state);
+                log::trace!("appending the output of {:?}", command);
 
-            Event::Up => {
-                log::trace!("moving selection up");
+                let source = data_input::command_task(command, active, engine_sender.clone());
+                sources[active].push(task::spawn(source));
+            }
+            Event::ClearList => {
+                log::trace!("clearing the list");
 
-                state.select_up();
+                clear_list(&mut sources[active], active, &engine_sender).await?;
+                state.clear_matches();
                 render = true;
             }
-            Event::Down => {
-                log::trace!("moving selection down");
+            Event::SwitchAlgo(algo) => {
+                log::trace!("matching with algorithm: {}", algo);
 
-                state.select_down();
+                state.set_algo(algo);
                 render = true;
             }
+            Event::SwitchMode(idx) => {
+                if let Some(mode) = config.modes.get(idx) {
+                    log::trace!("switching to mode {:?}", mode.name);
+
+                    active = idx;
+                    state.set_mode(Some(mode.name.clone()));
+                    state.clear_matches();
+                    render = true;
+                }
+            }
 
             Event::Done => {
                 selection = state.selection();
@@ -92,7 +268,49 @@ where
         }
     }
 
+    for source in sources.into_iter().flatten() {
+        source.cancel().await;
+    }
+
     log::trace!("screen done");
 
     Ok(selection)
 }
+
+// Stop the running commands of the pool and tell the engine to drop all its lines.
+//
+// The commands are stopped first, so none of their lines arrive to the engine after it clears
+// the pool
+async fn clear_list(
+    sources: &mut Vec<JoinHandle<Result<()>>>,
+    pool: usize,
+    engine_sender: &Sender<Event>,
+) -> Result<()> {
+    for source in sources.drain(..) {
+        source.cancel().await;
+    }
+
+    engine_sender.send(Event::ClearPool(pool)).await?;
+
+    Ok(())
+}
+
+// Move the selection around the list.
+//
+// Moving up and down is relative to what the person sees, so in the reverse layout, where
+// the best match is at the bottom of the list, moving up means going to the next match.
+fn navigate(state: &mut State, event: &Event, page_size: usize, reverse: bool) {
+    let half_page = (page_size / 2).max(1);
+
+    match (event, reverse) {
+        (Event::Up, false) | (Event::Down, true) => state.select_up(),
+        (Event::Down, false) | (Event::Up, true) => state.select_down(),
+        (Event::PageUp, false) | (Event::PageDown, true) => state.select_up_by(page_size),
+        (Event::PageDown, false) | (Event::PageUp, true) => state.select_down_by(page_size),
+        (Event::HalfPageUp, false) | (Event::HalfPageDown, true) => state.select_up_by(half_page),
+        (Event::HalfPageDown, false) | (Event::HalfPageUp, true) => state.select_down_by(half_page),
+        (Event::First, _) => state.select_first(),
+        (Event::Last, _) => state.select_last(),
+        _ => (),
+    }
+}
@@ -1,8 +1,10 @@
 //! The state of the program including interactions (moving around), last query, search
 //! results and current selection
 
-use crate::common::{Prompt, Text};
-use crate::fuzzy::Candidate;
+use crate::common::{PromptMode, Search, Text};
+use crate::fuzzy::{Algo, Candidate, Matcher, Matches};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Possible updates done to the State
 #[derive(Debug, Clone)]
@@ -22,19 +24,92 @@ impl Default for StateUpdate {
 /// Current state of the program
 #[derive(Debug, Clone, Default)]
 pub struct State {
-    search: Option<Prompt>,
+    search: Option<Search>,
+    header: Vec<String>,
     matches: Vec<Candidate>,
+    matches_count: usize,
+    matcher: Option<Arc<Matcher>>,
     pool_len: usize,
     selection_idx: usize,
+    marked: Vec<Text>,
+    cycle: bool,
+    mode: Option<String>,
+    algo: Algo,
+    loading: Option<Instant>,
+    truncated: bool,
+    search_time: Option<Duration>,
     last_update: StateUpdate,
 }
 
 impl State {
     pub fn new() -> Self {
-        Self::default()
+        Self {
+            cycle: true,
+            ..Default::default()
+        }
+    }
+
+    /// Whether moving the selection past the first or last match goes to the other end
+    /// of the list or not
+    pub fn set_cycle(&mut self, cycle: bool) {
+        self.cycle = cycle;
+    }
+
+    /// Name of the active mode, if there are modes
+    pub fn set_mode(&mut self, mode: Option<String>) {
+        self.mode = mode;
+        self.last_update = StateUpdate::All;
+    }
+
+    pub fn mode(&self) -> Option<&str> {
+        self.mode.as_deref()
+    }
+
+    /// Algorithm used to match the query
+    pub fn set_algo(&mut self, algo: Algo) {
+        self.algo = algo;
+        self.last_update = StateUpdate::All;
+    }
+
+    pub fn algo(&self) -> Algo {
+        self.algo
+    }
+
+    /// Whether there are still lines to come from the input
+    pub fn set_loading(&mut self, loading: bool) {
+        self.loading = match (loading, self.loading) {
+            (true, None) => Some(Instant::now()),
+            (true, since) => since,
+            (false, _) => None,
+        };
+        self.last_update = StateUpdate::All;
+    }
+
+    /// Since when the input is loading, if it is
+    pub fn loading_since(&self) -> Option<Instant> {
+        self.loading
+    }
+
+    /// Whether lines of the input have been dropped because of the pool limit
+    pub fn set_truncated(&mut self, truncated: bool) {
+        self.truncated = truncated;
+        self.last_update = StateUpdate::All;
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
     }
 
-    pub fn set_search(&mut self, search: Prompt) {
+    /// Time from the last query to its results
+    pub fn set_search_time(&mut self, time: Duration) {
+        self.search_time = Some(time);
+    }
+
+    pub fn search_time(&self) -> Option<Duration> {
+        self.search_time
+    }
+
+    pub fn set_search(&mut self, search: Search) {
         self.search = Some(search);
         self.last_update = StateUpdate::Query;
     }
@@ -46,6 +121,13 @@ impl State {
         }
     }
 
+    pub fn prompt_mode(&self) -> PromptMode {
+        match &self.search {
+            Some(sb) => sb.mode(),
+            None => Default::default(),
+        }
+    }
+
     pub fn cursor_until_end(&self) -> usize {
         match &self.search {
             Some(sb) => sb.cursor_until_end(),
@@ -53,8 +135,20 @@ impl State {
         }
     }
 
-    pub fn set_matches(&mut self, matches: (Vec<Candidate>, usize)) {
-        self.matches = matches.0;
+    pub fn set_header(&mut self, header: Vec<String>) {
+        self.header = header;
+        self.last_update = StateUpdate::All;
+    }
+
+    /// Lines from the input used as header
+    pub fn header(&self) -> &Vec<String> {
+        &self.header
+    }
+
+    /// Only the best matches are loaded, the rest of them are just counted
+    pub fn set_matches(&mut self, matches: (Matches, usize)) {
+        self.matches = matches.0.candidates;
+        self.matches_count = matches.0.count;
         self.pool_len = matches.1;
 
         if self.selection_idx >= self.max_selection() {
@@ -64,10 +158,36 @@ impl State {
         self.last_update = StateUpdate::All;
     }
 
+    /// Query used to find the current matches, to highlight them
+    pub fn set_matcher(&mut self, matcher: Arc<Matcher>) {
+        self.matcher = Some(matcher);
+    }
+
+    pub fn matcher(&self) -> Option<&Matcher> {
+        self.matcher.as_deref()
+    }
+
+    /// Remove all the matches and go back to the first position of the list. Marks are removed
+    /// too, since they belong to the lines that are gone
+    pub fn clear_matches(&mut self) {
+        self.matches.clear();
+        self.matches_count = 0;
+        self.pool_len = 0;
+        self.selection_idx = 0;
+        self.marked.clear();
+        self.last_update = StateUpdate::All;
+    }
+
+    /// Loaded matches, from the best one
     pub fn matches(&self) -> &Vec<Candidate> {
         &self.matches
     }
 
+    /// Number of candidates that match the query, loaded or not
+    pub fn matches_count(&self) -> usize {
+        self.matches_count
+    }
+
     pub fn pool_len(&self) -> usize {
         self.pool_len
     }
@@ -78,7 +198,9 @@ impl State {
 
     pub fn select_up(&mut self) {
         if self.selection_idx == 0 {
-            self.selection_idx = self.max_selection();
+            if self.can_cycle() {
+                self.selection_idx = self.max_selection();
+            }
         } else {
             self.selection_idx -= 1;
         }
@@ -87,30 +209,250 @@ impl State {
 
     pub fn select_down(&mut self) {
         if self.selection_idx == self.max_selection() {
-            self.selection_idx = 0;
+            if self.can_cycle() {
+                self.selection_idx = 0;
+            }
         } else {
             self.selection_idx += 1;
         }
         self.last_update = StateUpdate::All;
     }
 
+    /// Move the selection up the given number of matches, stopping at the first one
+    pub fn select_up_by(&mut self, count: usize) {
+        self.selection_idx = self.selection_idx.saturating_sub(count);
+        self.last_update = StateUpdate::All;
+    }
+
+    /// Move the selection down the given number of matches, stopping at the last one
+    pub fn select_down_by(&mut self, count: usize) {
+        self.selection_idx = (self.selection_idx + count).min(self.max_selection());
+        self.last_update = StateUpdate::All;
+    }
+
+    /// Select the match in the given position, if it exists
+    pub fn select(&mut self, idx: usize) {
+        if idx <= self.max_selection() {
+            self.selection_idx = idx;
+            self.last_update = StateUpdate::All;
+        }
+    }
+
+    pub fn select_first(&mut self) {
+        self.selection_idx = 0;
+        self.last_update = StateUpdate::All;
+    }
+
+    pub fn select_last(&mut self) {
+        self.selection_idx = self.max_selection();
+        self.last_update = StateUpdate::All;
+    }
+
     pub fn selection_idx(&self) -> usize {
         self.selection_idx
     }
 
+    /// Whether every match is loaded. Only loaded matches can be selected
+    pub fn all_loaded(&self) -> bool {
+        self.matches.len() >= self.matches_count
+    }
+
+    /// Selected match. It's `None` if there are no matches
     pub fn selection(&self) -> Option<Text> {
         self.matches
             .get(self.selection_idx)
             .map(|candidate| candidate.text.clone())
     }
 
+    /// Mark the selected match, or unmark it if it was already marked.
+    ///
+    /// Marks are kept while the query changes, even for lines that don't match anymore
+    pub fn toggle_mark(&mut self) {
+        if let Some(selection) = self.selection() {
+            match self.marked.iter().position(|text| *text == selection) {
+                Some(idx) => {
+                    self.marked.remove(idx);
+                }
+                None => self.marked.push(selection),
+            }
+            self.last_update = StateUpdate::All;
+        }
+    }
+
+    pub fn is_marked(&self, text: &Text) -> bool {
+        self.marked.contains(text)
+    }
+
+    /// Marked lines, in the order they were marked
+    pub fn marked(&self) -> &[Text] {
+        &self.marked
+    }
+
     fn max_selection(&self) -> usize {
-        let len = self.matches.len();
+        self.matches.len().saturating_sub(1)
+    }
 
-        if len == 0 {
-            0
-        } else {
-            len - 1
-        }
+    // The other end of the list is only known once all the matches are loaded
+    fn can_cycle(&self) -> bool {
+        self.cycle && self.all_loaded()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::TextBuilder;
+
+    fn state_with_matches(count: usize) -> State {
+        let matches = (0..count)
+            .map(|n| Candidate::from(&TextBuilder::build(&n.to_string())))
+            .collect::<Vec<_>>();
+
+        let mut state = State::new();
+        state.set_matches((matches.into(), count));
+
+        state
+    }
+
+    #[test]
+    fn select_up_and_down_cycle_test() {
+        let mut state = state_with_matches(3);
+
+        state.select_up();
+        assert_eq!(state.selection_idx(), 2);
+
+        state.select_down();
+        assert_eq!(state.selection_idx(), 0);
+    }
+
+    #[test]
+    fn clear_matches_test() {
+        let mut state = state_with_matches(3);
+        state.select_last();
+
+        state.clear_matches();
+        assert_eq!(state.selection_idx(), 0);
+        assert_eq!(state.pool_len(), 0);
+        assert!(state.selection().is_none());
+    }
+
+    #[test]
+    fn select_only_loaded_matches_test() {
+        let mut state = State::new();
+        let candidates = vec![
+            Candidate::from(&TextBuilder::build("first")),
+            Candidate::from(&TextBuilder::build("second")),
+        ];
+        state.set_matches((
+            Matches {
+                candidates,
+                count: 10,
+            },
+            20,
+        ));
+
+        assert_eq!(state.matches_count(), 10);
+        assert!(!state.all_loaded());
+
+        state.select_last();
+        assert_eq!(state.selection_idx(), 1);
+        assert_eq!(state.selection().unwrap().to_string(), "second");
+
+        // The rest of the matches are not loaded, so there is no other end to go to
+        state.select_down();
+        assert_eq!(state.selection_idx(), 1);
+
+        state.select_first();
+        state.select_up();
+        assert_eq!(state.selection_idx(), 0);
+
+        state.select(5);
+        assert_eq!(state.selection_idx(), 0);
+    }
+
+    #[test]
+    fn toggle_mark_test() {
+        let mut state = state_with_matches(3);
+
+        state.select_last();
+        state.toggle_mark();
+        state.select_first();
+        state.toggle_mark();
+
+        let marked = state.marked().iter().map(|text| text.to_string());
+        assert_eq!(marked.collect::<Vec<_>>(), vec!["2", "0"]);
+        assert!(state.is_marked(&TextBuilder::build("2")));
+        assert!(!state.is_marked(&TextBuilder::build("1")));
+
+        // Marks stay with the lines when the matches change
+        state.set_matches((vec![Candidate::from(&TextBuilder::build("1"))].into(), 3));
+        assert_eq!(state.marked().len(), 2);
+
+        state.toggle_mark();
+        state.set_matches((vec![Candidate::from(&TextBuilder::build("2"))].into(), 3));
+        state.toggle_mark();
+        let marked = state.marked().iter().map(|text| text.to_string());
+        assert_eq!(marked.collect::<Vec<_>>(), vec!["0", "1"]);
+
+        state.clear_matches();
+        state.toggle_mark();
+        assert!(state.marked().is_empty());
+    }
+
+    #[test]
+    fn loading_test() {
+        let mut state = State::new();
+        assert!(state.loading_since().is_none());
+
+        state.set_loading(true);
+        let since = state.loading_since();
+        assert!(since.is_some());
+
+        state.set_loading(true);
+        assert_eq!(state.loading_since(), since);
+
+        state.set_loading(false);
+        assert!(state.loading_since().is_none());
+    }
+
+    #[test]
+    fn select_up_and_down_without_cycle_test() {
+        let mut state = state_with_matches(3);
+        state.set_cycle(false);
+
+        state.select_up();
+        assert_eq!(state.selection_idx(), 0);
+
+        state.select_last();
+        state.select_down();
+        assert_eq!(state.selection_idx(), 2);
+    }
+
+    #[test]
+    fn select_by_count_test() {
+        let mut state = state_with_matches(10);
+
+        state.select_down_by(4);
+        assert_eq!(state.selection_idx(), 4);
+
+        state.select_down_by(20);
+        assert_eq!(state.selection_idx(), 9);
+
+        state.select_up_by(3);
+        assert_eq!(state.selection_idx(), 6);
+
+        state.select_up_by(20);
+        assert_eq!(state.selection_idx(), 0);
+    }
+
+    #[test]
+    fn select_first_and_last_test() {
+        let mut state = state_with_matches(5);
+
+        state.select_last();
+        assert_eq!(state.selection_idx(), 4);
+
+        state.select_first();
+        assert_eq!(state.selection_idx(), 0);
     }
 }
@@ -29,6 +29,16 @@
 //! only relevant to the screen. Others, like new queries, are relevant for both. Using these two
 //! channels also makes the screen more responsive to interactions since it doesn't have to wait
 //! for the engine to finish searching in order to update the prompt, for example.
+//!
+//! When a command bound to a key takes over the terminal the person's input stops reading until
+//! the screen tells it the command is done, through a channel from the screen back to the
+//! person's input.
+//!
+//! Commands that change the list while the program runs (`reload(CMD)`, `append(CMD)` and
+//! `clear-list`) are started by the screen, which sends their lines to the engine through the
+//! same channel used by `data_input`. The screen also starts the commands of the modes given in
+//! the config, in which case `data_input` doesn't read `STDIN` at all. Neither does it when it
+//! follows a file or reads the files given as arguments (unless one of them is `-`).
 
 use crate::common::{Result, Text};
 use crate::config::Config;
@@ -38,6 +48,7 @@ use crate::events::Event;
 use crate::person_input;
 use crate::screen;
 use async_std::channel::{self, Receiver, Sender};
+use async_std::fs::{self, File};
 use async_std::io;
 use async_std::task;
 
@@ -50,19 +61,65 @@ where
     I: io::Read + Send + Unpin + 'static,
     W: io::Write + Send + Unpin + 'static,
 {
+    let has_modes = !config.modes.is_empty();
+    // A followed file is the only source of lines, nothing else would be read
+    if config.follow.is_some() && (has_modes || !config.files.is_empty()) {
+        return Err("--follow can't be used with modes or files to read".into());
+    }
+    // Open the followed file here, so the program stops right away if it can't be read
+    let follow = match &config.follow {
+        Some(path) => {
+            let file = File::open(path)
+                .await
+                .map_err(|e| format!("can't follow {:?}: {}", path, e))?;
+            Some((path.clone(), file))
+        }
+        _ => None,
+    };
+    // Same with the files to read
+    for path in config.files.iter().filter(|path| *path != "-") {
+        fs::metadata(path)
+            .await
+            .map_err(|e| format!("can't read {:?}: {}", path, e))?;
+    }
+
     // channels
     let (input_sender, input_recv) = channel();
     let (output_sender, output_recv) = channel();
+    let (person_sender, person_recv) = channel();
 
-    let screen_task = task::spawn(screen::task(config.clone(), outbox, output_recv));
+    let screen_task = task::spawn(screen::task(
+        config.clone(),
+        outbox,
+        output_recv,
+        person_sender,
+        input_sender.clone(),
+    ));
     let person_task = task::spawn(person_input::task(
         config.clone(),
         inbox,
+        person_recv,
         input_sender.clone(),
         output_sender.clone(),
     ));
-    let engine_task = task::spawn(engine::task(config, input_recv, output_sender));
-    let data_task = task::spawn(data_input::task(stdin, input_sender));
+    let engine_task = task::spawn(engine::task(config.clone(), input_recv, output_sender));
+    // With modes the lines come from the modes' commands instead of STDIN,
+    // and a followed file or the given files replace STDIN too
+    let data_task = match (has_modes, follow) {
+        (true, _) => None,
+        (false, Some((path, file))) => Some(task::spawn(data_input::follow_task(
+            path,
+            file,
+            input_sender,
+        ))),
+        (false, None) if !config.files.is_empty() => Some(task::spawn(data_input::files_task(
+            config.files.clone(),
+            config.with_filename,
+            stdin,
+            input_sender,
+        ))),
+        (false, None) => Some(task::spawn(data_input::task(stdin, input_sender))),
+    };
 
     let selection = screen_task.await;
 
@@ -2,8 +2,11 @@
 
 mod components;
 mod convert;
+mod layout;
 
+pub use components::SPINNER_INTERVAL;
 use components::*;
+use layout::{up, Areas, Frame, Rect};
 
 use crate::common::Result;
 use crate::config::Config;
@@ -15,6 +18,14 @@ use termion::{clear, cursor};
 
 const ALTERNATE_SCREEN: &str = csi!("?1049h");
 const MAIN_SCREEN: &str = csi!("?1049l");
+// Report mouse clicks and wheel events using the SGR encoding
+const ENABLE_MOUSE: &str = csi!("?1000h\x1b[?1006h");
+const DISABLE_MOUSE: &str = csi!("?1006l\x1b[?1000l");
+// Pasted text is surrounded by `ESC [200~` and `ESC [201~`
+const ENABLE_BRACKETED_PASTE: &str = csi!("?2004h");
+const DISABLE_BRACKETED_PASTE: &str = csi!("?2004l");
+// Ask the terminal for the cursor position
+const CURSOR_POSITION: &str = csi!("6n");
 
 #[derive(Debug, Clone)]
 enum Mode {
@@ -27,36 +38,66 @@ impl Mode {
     //
     // * In full screen we want to go to an "Alternate screen". Basically the terminal changes to
     //   another clean "window".
-    // * In inline mode we want to make enough room to be able to print lines under the cursor
-    pub fn setup(&self) -> Option<String> {
-        let setup = match self {
-            Self::Full => format!("{}{}", ALTERNATE_SCREEN, cursor::Goto(1, 1)),
+    // * In inline mode we want to make enough room to be able to print lines under the cursor.
+    //   The UI starts in the current line, so printing new lines for the rest of the UI makes the
+    //   terminal scroll up when the cursor is near the bottom and there isn't enough room below
+    //
+    // The cursor is always left in the line where the prompt lives, which depends on the layout
+    // and the decorations around the UI.
+    //
+    // Bracketed paste is always enabled, so pasted text can be told apart from typed keys.
+    //
+    // When the mouse is enabled the terminal reports its events with the same coordinates for
+    // both modes, so in inline mode we ask for the cursor position to find out where the UI is.
+    pub fn setup(&self, prompt_row: usize, mouse: bool) -> Option<String> {
+        let mut setup = match self {
+            Self::Full => format!(
+                "{}{}",
+                ALTERNATE_SCREEN,
+                cursor::Goto(1, (prompt_row + 1) as u16)
+            ),
             Self::Inline(height) => {
                 let room = std::iter::repeat("\n")
-                    .take(*height)
+                    .take(*height - 1)
                     .collect::<Vec<&str>>()
                     .join("");
 
-                let up = *height as u16;
-
-                format!("{}{}\r", room, cursor::Up(up))
+                format!("{}{}\r", room, up(*height - 1 - prompt_row))
             }
         };
 
+        setup.push_str(ENABLE_BRACKETED_PASTE);
+
+        if mouse {
+            setup.push_str(ENABLE_MOUSE);
+
+            if let Self::Inline(_) = self {
+                setup.push_str(CURSOR_POSITION);
+            }
+        }
+
         Some(setup)
     }
 
     // After finishing with the program we want to restore the screen
     //
     // * In full mode that means going back to the main screen, with no changes
-    // * In inline mode that means cleaning the last line to print the result
-    pub fn teardown(&self) -> Option<String> {
+    // * In inline mode that means cleaning all the lines used by the UI and going back
+    //   to the first one to print the result
+    pub fn teardown(&self, prompt_row: usize, mouse: bool) -> Option<String> {
         let teardown = match self {
             Self::Full => MAIN_SCREEN.to_string(),
-            Self::Inline(_) => format!("{}{}{}", clear::CurrentLine, clear::AfterCursor, "\r"),
+            Self::Inline(_) => format!("{}\r{}", up(prompt_row), clear::AfterCursor),
         };
 
-        Some(teardown)
+        if mouse {
+            return Some(format!(
+                "{}{}{}",
+                DISABLE_BRACKETED_PASTE, DISABLE_MOUSE, teardown
+            ));
+        }
+
+        Some(format!("{}{}", DISABLE_BRACKETED_PASTE, teardown))
     }
 }
 
@@ -64,34 +105,49 @@ impl Mode {
 #[derive(Debug)]
 pub struct Canvas<W: io::Write + Send + Unpin + 'static> {
     mode: Mode,
+    frame: Frame,
+    areas: Areas,
+    mouse: bool,
+    // Line of the terminal where the UI starts, starting from 1
+    origin: Option<usize>,
     writer: W,
     prompt: PromptComponent,
     gauge: GaugeComponent,
+    header: HeaderComponent,
     list: ListComponent,
 }
 
 impl<W: io::Write + Send + Unpin + 'static> Canvas<W> {
     pub async fn new(config: &Config, writer: W) -> Result<Self> {
-        let mode = if config.screen.is_full() {
-            Mode::Full
+        let frame: Frame = config.into();
+        let (mode, origin) = if config.screen.is_full() {
+            (Mode::Full, Some(1))
         } else {
-            let height = config.screen.height();
-            Mode::Inline(height)
+            (Mode::Inline(frame.height()), None)
         };
+        let mouse = config.mouse;
+        let layout = config.screen.layout();
+        let areas = Areas::new(frame.inner(), config.header.height(), layout.is_reverse());
 
-        let prompt = config.into();
-        let gauge = config.into();
-        let list = config.into();
+        let prompt = PromptComponent::new(config, &areas.prompt);
+        let gauge = GaugeComponent::new(config, &areas.gauge);
+        let header = HeaderComponent::new(config, &areas.header);
+        let list = ListComponent::new(config, &areas.list);
 
         let mut canvas = Self {
             mode,
+            frame,
+            areas,
+            mouse,
+            origin,
             writer,
             prompt,
             gauge,
+            header,
             list,
         };
 
-        if let Some(setup) = canvas.mode.setup() {
+        if let Some(setup) = canvas.mode.setup(canvas.prompt_row(), canvas.mouse) {
             canvas.write(&setup).await?;
         }
 
@@ -105,39 +161,125 @@ impl<W: io::Write + Send + Unpin + 'static> Canvas<W> {
     pub async fn render(&mut self, state: &State) -> Result<()> {
         match state.last_update() {
             StateUpdate::Query => {
-                let display = format!("{}\r{}", clear::CurrentLine, self.prompt.render(state));
+                let prompt = self.prompt.render(state).to_string();
+                let display = format!("{}\r{}", clear::CurrentLine, self.frame.row(&prompt));
                 self.write(&display).await?;
             }
-            _ => {
-                self.list.scroll(state);
-
-                let list_renderer = self.list.render(state);
-                let list_len = list_renderer.len();
-
-                // Only add a new line if we are going to print items
-                let gauge_separator = if list_len == 0 { "" } else { "\n" };
-
-                let display = format!(
-                    "{down}{clrl}\r{gauge}{gauge_sep}{list}{clra}{up}{clrl}\r{prompt}",
-                    clrl = clear::CurrentLine,
-                    down = cursor::Down(1),
-                    gauge = self.gauge.render(state),
-                    gauge_sep = gauge_separator,
-                    list = list_renderer,
-                    clra = clear::AfterCursor,
-                    // By going up and printing as the last element the prompt we ensure the cursor
-                    // is in the right position
-                    up = cursor::Up((list_len + 1) as u16),
-                    prompt = self.prompt.render(state),
-                );
+            _ => self.draw(state).await?,
+        }
 
-                self.write(&display).await?;
-            }
+        Ok(())
+    }
+
+    /// Give the terminal back, like when the program finishes, so other programs can use it
+    pub async fn suspend(&mut self) -> Result<()> {
+        if let Some(teardown) = self.mode.teardown(self.prompt_row(), self.mouse) {
+            self.write(&teardown).await?;
         }
 
         Ok(())
     }
 
+    /// Take the terminal again after suspending the UI and draw everything
+    pub async fn resume(&mut self, state: &State) -> Result<()> {
+        if let Mode::Inline(_) = self.mode {
+            // The UI will start wherever the cursor is now
+            self.origin = None;
+        }
+
+        if let Some(setup) = self.mode.setup(self.prompt_row(), self.mouse) {
+            self.write(&setup).await?;
+        }
+
+        self.draw(state).await
+    }
+
+    /// Set the line of the terminal where the cursor was after setting up the UI
+    pub fn set_cursor_position(&mut self, line: u16) {
+        self.origin = (line as usize).checked_sub(self.prompt_row());
+    }
+
+    /// Index of the candidate printed in the given line of the terminal, if any
+    pub fn candidate_at(&self, line: u16, state: &State) -> Option<usize> {
+        let origin = self.origin?;
+        let row = (line as usize).checked_sub(origin)?;
+        let area = &self.areas.list;
+
+        if row < area.y || row >= area.y + area.height {
+            return None;
+        }
+
+        let position = if self.list.reverse {
+            area.y + area.height - 1 - row
+        } else {
+            row - area.y
+        };
+        let idx = self.list.offset + position;
+
+        if idx < state.matches().len() {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+
+    /// Number of candidates visible at once in the list
+    pub fn page_size(&self) -> usize {
+        self.list.lines.max(1)
+    }
+
+    /// Line of the UI where the prompt is printed, which is also where the cursor lives
+    fn prompt_row(&self) -> usize {
+        self.areas.prompt.y
+    }
+
+    // Draw the whole UI
+    async fn draw(&mut self, state: &State) -> Result<()> {
+        self.list.scroll(state);
+
+        let prompt = self.prompt.render(state).to_string();
+
+        // Lines of the inner area, from top to bottom
+        let inner = self.frame.inner();
+        let mut content = vec![String::new(); inner.height];
+        let mut place = |area: &Rect, rows: Vec<String>| {
+            let start = area.y - inner.y;
+            for (idx, row) in rows.into_iter().take(area.height).enumerate() {
+                content[start + idx] = row;
+            }
+        };
+
+        place(&self.areas.prompt, vec![prompt.clone()]);
+        place(
+            &self.areas.gauge,
+            vec![self.gauge.render(state).to_string()],
+        );
+        place(&self.areas.header, self.header.render(state).rows());
+        place(&self.areas.list, self.list.render(state).rows());
+
+        let rows = self
+            .frame
+            .rows(&content)
+            .into_iter()
+            .map(|row| format!("{}{}", clear::CurrentLine, row))
+            .collect::<Vec<String>>();
+
+        // Go to the top of the UI and print everything downwards. By going back to the
+        // prompt's line and printing it again we ensure the cursor is in the right
+        // position
+        let display = format!(
+            "{top}\r{rows}{back}\r{prompt}",
+            top = up(self.prompt_row()),
+            rows = rows.join("\n"),
+            back = up(self.frame.height() - 1 - self.prompt_row()),
+            prompt = self.frame.row(&prompt),
+        );
+
+        self.write(&display).await?;
+
+        Ok(())
+    }
+
     async fn write(&mut self, display: &str) -> Result<()> {
         self.writer.write_all(display.as_bytes()).await?;
         self.writer.flush().await?;
@@ -149,7 +291,7 @@ impl<W: io::Write + Send + Unpin + 'static> Canvas<W> {
 impl<W: io::Write + Send + Unpin + 'static> Drop for Canvas<W> {
     fn drop(&mut self) {
         task::block_on(async {
-            if let Some(teardown) = self.mode.teardown() {
+            if let Some(teardown) = self.mode.teardown(self.prompt_row(), self.mouse) {
                 self.write(&teardown)
                     .await
                     .expect("Error writing to output");
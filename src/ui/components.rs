@@ -9,13 +9,20 @@
 //! This two steps process for printing is done so we only need the state information while
 //! printing and not before, which means we can use references to get the data and prevent any
 //! extra data allocation from the state to the components.
+use super::layout::{clip, grapheme_width, text_width, Rect};
+use crate::common::{self, PromptMode};
 use crate::config::Config;
-use crate::fuzzy::Candidate;
+use crate::fuzzy::{self, Algo, Candidate};
 use crate::state::State;
 use ansi_term::{ANSIString, ANSIStrings, Style};
-use std::convert::From;
 use std::fmt;
-use termion::{clear, cursor};
+use std::time::Duration;
+use termion::cursor;
+use unicode_width::UnicodeWidthChar;
+
+/// Time each frame of the loading spinner is shown
+pub const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+const SPINNER: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
 pub trait Render<'r, R>
 where
@@ -32,11 +39,39 @@ pub struct PromptRenderer<'r> {
 
 impl<'r> fmt::Display for PromptRenderer<'r> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let query = self.state.query().chars().collect::<Vec<char>>();
+        let cursor = query.len() - self.state.cursor_until_end();
+        let symbol = match self.state.prompt_mode() {
+            PromptMode::Insert => &self.prompt.symbol,
+            PromptMode::Normal => &self.prompt.symbol_normal,
+        };
+
+        // When the query doesn't fit in the prompt it is scrolled horizontally,
+        // always keeping the cursor visible. The cursor takes one column after the query
+        let available = self.prompt.width.saturating_sub(text_width(symbol)).max(1);
+        let width = |ch: &char| ch.width().unwrap_or(0);
+
+        let mut start = cursor;
+        let mut used = 1;
+        while start > 0 && used + width(&query[start - 1]) <= available {
+            start -= 1;
+            used += width(&query[start]);
+        }
+
+        let mut end = cursor;
+        let mut used = query[start..cursor].iter().map(width).sum::<usize>();
+        while end < query.len() && used + width(&query[end]) <= available {
+            used += width(&query[end]);
+            end += 1;
+        }
+
+        let visible = query[start..end].iter().collect::<String>();
+
         let strings: Vec<ANSIString<'_>> = vec![
-            self.prompt.style_symbol.paint(&self.prompt.symbol),
-            self.prompt.style.paint(self.state.query()),
+            self.prompt.style_symbol.paint(symbol),
+            self.prompt.style.paint(&visible),
         ];
-        let left_moves = self.state.cursor_until_end() as u16;
+        let left_moves = query[cursor..end].iter().map(width).sum::<usize>() as u16;
 
         if left_moves == 0 {
             write!(f, "{}", ANSIStrings(&strings))
@@ -48,7 +83,9 @@ impl<'r> fmt::Display for PromptRenderer<'r> {
 
 #[derive(Debug)]
 pub struct PromptComponent {
+    pub width: usize,
     pub symbol: String,
+    pub symbol_normal: String,
     pub style: Style,
     pub style_symbol: Style,
 }
@@ -62,10 +99,12 @@ impl<'r> Render<'r, PromptRenderer<'r>> for PromptComponent {
     }
 }
 
-impl From<&Config> for PromptComponent {
-    fn from(config: &Config) -> Self {
+impl PromptComponent {
+    pub fn new(config: &Config, area: &Rect) -> Self {
         Self {
+            width: area.width,
             symbol: config.prompt.symbol(),
+            symbol_normal: config.prompt.symbol_normal(),
             style: config.prompt.style().into(),
             style_symbol: config.prompt.style_symbol().into(),
         }
@@ -80,35 +119,85 @@ pub struct GaugeRenderer<'r> {
 
 impl<'r> fmt::Display for GaugeRenderer<'r> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let current = self.state.matches().len();
-        let total = self.state.pool_len();
+        let current = self.state.matches_count().to_string();
+        let total = self.state.pool_len().to_string();
+        let marked = self.state.marked().len().to_string();
+        let truncated = if self.state.is_truncated() { "+" } else { "" };
+        let spinner = self.state.loading_since().map(|since| {
+            let frame = since.elapsed().as_millis() / SPINNER_INTERVAL.as_millis();
+            SPINNER[frame as usize % SPINNER.len()]
+        });
+
+        let mut gauge = self.gauge.prefix.clone();
+
+        match &self.gauge.format {
+            Some(format) => {
+                let time = self
+                    .state
+                    .search_time()
+                    .unwrap_or_default()
+                    .as_millis()
+                    .to_string();
+                let placeholders = [
+                    ("{matches}", current.as_str()),
+                    ("{total}", total.as_str()),
+                    ("{marked}", marked.as_str()),
+                    ("{spinner}", spinner.unwrap_or_default()),
+                    ("{time}", time.as_str()),
+                    ("{truncated}", truncated),
+                    ("{mode}", self.state.mode().unwrap_or_default()),
+                    ("{algo}", self.state.algo().name()),
+                ];
+
+                gauge.push_str(&common::expand(format, &placeholders));
+            }
+            None => {
+                gauge.push_str(&format!(
+                    "{}{}{}{}",
+                    current, self.gauge.symbol, total, truncated
+                ));
+
+                if let Some(spinner) = spinner {
+                    gauge.push(' ');
+                    gauge.push_str(spinner);
+                }
+
+                if let Some(mode) = self.state.mode() {
+                    gauge.push_str(&format!(" [{}]", mode));
+                }
+
+                // Fuzzy matching is what everyone expects, the other algorithms are shown
+                if self.state.algo() != Algo::Fuzzy {
+                    gauge.push_str(&format!(" ({})", self.state.algo()));
+                }
+            }
+        }
 
         write!(
             f,
-            "{}{}{}{}{}{}",
-            self.gauge.style.prefix(),
-            self.gauge.prefix,
-            current,
-            self.gauge.symbol,
-            total,
-            self.gauge.style.suffix()
+            "{}",
+            self.gauge.style.paint(clip(&gauge, self.gauge.width))
         )
     }
 }
 
 #[derive(Debug)]
 pub struct GaugeComponent {
+    pub width: usize,
     pub symbol: String,
     pub prefix: String,
+    pub format: Option<String>,
     pub style: Style,
 }
 
-impl From<&Config> for GaugeComponent {
-    fn from(config: &Config) -> Self {
+impl GaugeComponent {
+    pub fn new(config: &Config, area: &Rect) -> Self {
         Self {
+            width: area.width,
             style: config.gauge.style().into(),
             symbol: config.gauge.symbol(),
             prefix: config.gauge.prefix(),
+            format: config.gauge.format(),
         }
     }
 }
@@ -119,10 +208,80 @@ impl<'r> Render<'r, GaugeRenderer<'r>> for GaugeComponent {
     }
 }
 
+#[derive(Debug)]
+pub struct HeaderRenderer<'r> {
+    header: &'r HeaderComponent,
+    state: &'r State,
+}
+
+impl<'r> HeaderRenderer<'r> {
+    /// All the lines of the header from top to bottom: first the static text and then
+    /// the lines from the input
+    pub fn rows(&self) -> Vec<String> {
+        let mut rows = self
+            .header
+            .text
+            .iter()
+            .chain(self.state.header().iter())
+            .take(self.header.lines)
+            .map(|line| {
+                let line = format!("{}{}", self.header.indent, line);
+
+                self.header
+                    .style
+                    .paint(clip(&line, self.header.width))
+                    .to_string()
+            })
+            .collect::<Vec<String>>();
+
+        rows.resize(self.header.lines, String::new());
+
+        rows
+    }
+}
+
+impl<'r> fmt::Display for HeaderRenderer<'r> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.rows().join("\n"))
+    }
+}
+
+#[derive(Debug)]
+pub struct HeaderComponent {
+    pub width: usize,
+    pub lines: usize,
+    pub indent: String,
+    pub text: Vec<String>,
+    pub style: Style,
+}
+
+impl HeaderComponent {
+    pub fn new(config: &Config, area: &Rect) -> Self {
+        Self {
+            width: area.width,
+            lines: area.height,
+            // Align the header with the text of the candidates
+            indent: " ".repeat(text_width(&config.candidate.symbol())),
+            text: config.header.text(),
+            style: config.header.style().into(),
+        }
+    }
+}
+
+impl<'r> Render<'r, HeaderRenderer<'r>> for HeaderComponent {
+    fn render(&'r self, state: &'r State) -> HeaderRenderer<'r> {
+        HeaderRenderer {
+            header: self,
+            state,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ItemStyles {
     pub width: usize,
     pub symbol: String,
+    pub symbol_marked: String,
     pub style: Style,
     pub style_match: Style,
     pub style_symbol: Style,
@@ -132,6 +291,7 @@ impl ItemStyles {
     fn new(
         width: usize,
         symbol: String,
+        symbol_marked: String,
         style: Style,
         style_match: Style,
         style_symbol: Style,
@@ -139,6 +299,7 @@ impl ItemStyles {
         Self {
             width,
             symbol,
+            symbol_marked,
             style,
             style_match,
             style_symbol,
@@ -153,120 +314,129 @@ pub struct ListRenderer<'r> {
 }
 
 impl<'r> ListRenderer<'r> {
-    pub fn len(&'r self) -> usize {
-        let lines = self.list.height - 2;
-        let matches_len = self.state.matches().len();
-
-        let len = if matches_len >= self.list.offset {
-            matches_len - self.list.offset
-        } else {
-            self.list.offset - matches_len
-        };
-
-        if len >= lines {
-            lines
-        } else {
-            len
-        }
-    }
-}
-
-impl<'r> fmt::Display for ListRenderer<'r> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let lines = self.list.height - 2;
+    /// All the lines of the list from top to bottom, empty lines included.
+    pub fn rows(&self) -> Vec<String> {
+        // Searches don't keep the positions of the matches, they are only needed for
+        // the visible candidates
+        let matcher = self.state.matcher().filter(|matcher| !matcher.is_empty());
 
-        let mut items = self
+        let mut rows = self
             .state
             .matches()
             .iter()
             .enumerate()
             .skip(self.list.offset)
-            .take(lines)
-            .peekable();
-
-        while let Some((idx, candidate)) = items.next() {
-            let eol = if items.peek().is_none() { "" } else { "\n" };
-
-            let styles = if idx == self.state.selection_idx() {
-                &self.list.selection_styles
-            } else {
-                &self.list.candidate_styles
-            };
-
-            render_item(f, candidate, styles, eol)?
+            .take(self.list.lines)
+            .map(|(idx, candidate)| {
+                let styles = if idx == self.state.selection_idx() {
+                    &self.list.selection_styles
+                } else {
+                    &self.list.candidate_styles
+                };
+
+                let matches = match matcher {
+                    Some(matcher) if candidate.matches.is_empty() => {
+                        fuzzy::positions(matcher, candidate)
+                    }
+                    _ => candidate.matches.clone(),
+                };
+
+                ItemRenderer {
+                    candidate,
+                    matches: &matches,
+                    marked: self.state.is_marked(&candidate.text),
+                    styles,
+                }
+                .to_string()
+            })
+            .collect::<Vec<String>>();
+
+        rows.resize(self.list.lines, String::new());
+
+        // The best match goes at the bottom, closest to the prompt. Empty lines go first
+        // so the list always sits right above the gauge
+        if self.list.reverse {
+            rows.reverse();
         }
 
-        Ok(())
+        rows
     }
 }
 
-fn render_item(
-    f: &mut fmt::Formatter<'_>,
-    candidate: &Candidate,
-    styles: &ItemStyles,
-    eol: &str,
-) -> fmt::Result {
-    let symbol = &styles.symbol;
-    let style = &styles.style;
-    let style_match = &styles.style_match;
-    let style_symbol = &styles.style_symbol;
-
-    let mut strings: Vec<ANSIString<'_>> = vec![style_symbol.paint(symbol)];
-    let mut painted: Vec<ANSIString<'_>> = candidate
-        .iter()
-        .enumerate()
-        .take(styles.width - symbol.len())
-        .map(|(index, grapheme)| {
-            if candidate.matches.contains(&index) {
-                style_match.paint(grapheme)
-            } else {
-                style.paint(grapheme)
-            }
-        })
-        .collect();
+impl<'r> fmt::Display for ListRenderer<'r> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.rows().join("\n"))
+    }
+}
 
-    strings.append(&mut painted);
+#[derive(Debug)]
+struct ItemRenderer<'r> {
+    candidate: &'r Candidate,
+    matches: &'r [usize],
+    marked: bool,
+    styles: &'r ItemStyles,
+}
 
-    // ANSIStrings already takes care of reducing the number of escape
-    // sequences that will be printed to the terminal
-    write!(f, "{}{}{}", clear::CurrentLine, ANSIStrings(&strings), eol)
+impl<'r> fmt::Display for ItemRenderer<'r> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let candidate = self.candidate;
+        let symbol = if self.marked {
+            &self.styles.symbol_marked
+        } else {
+            &self.styles.symbol
+        };
+        let style = &self.styles.style;
+        let style_match = &self.styles.style_match;
+        let style_symbol = &self.styles.style_symbol;
+
+        let available = self.styles.width.saturating_sub(text_width(symbol));
+        let mut used = 0;
+
+        let mut strings: Vec<ANSIString<'_>> = vec![style_symbol.paint(symbol)];
+        let mut painted: Vec<ANSIString<'_>> = candidate
+            .iter()
+            .enumerate()
+            .take_while(|(_, grapheme)| {
+                used += grapheme_width(grapheme);
+                used <= available
+            })
+            .map(|(index, grapheme)| {
+                if self.matches.contains(&index) {
+                    style_match.paint(grapheme)
+                } else {
+                    style.paint(grapheme)
+                }
+            })
+            .collect();
+
+        strings.append(&mut painted);
+
+        // ANSIStrings already takes care of reducing the number of escape
+        // sequences that will be printed to the terminal
+        write!(f, "{}", ANSIStrings(&strings))
+    }
 }
 
 #[derive(Debug)]
 pub struct ListComponent {
-    pub height: usize,
+    pub lines: usize,
     pub offset: usize,
+    pub reverse: bool,
     pub candidate_styles: ItemStyles,
     pub selection_styles: ItemStyles,
 }
 
 impl ListComponent {
-    pub fn scroll(&mut self, state: &State) {
-        let len = self.height - 2;
-
-        let selection = state.selection_idx();
-
-        let top_position = self.offset;
-        let last_position = (len + self.offset) - 1;
-
-        // cycle through the list
-        if selection > last_position {
-            self.offset += selection - last_position;
-        } else if selection < top_position {
-            self.offset -= top_position - selection;
-        };
-    }
-}
-
-impl From<&Config> for ListComponent {
-    fn from(config: &Config) -> Self {
+    pub fn new(config: &Config, area: &Rect) -> Self {
         let offset = 0;
-        let height = config.screen.height();
-        let width = config.screen.width();
+        let lines = area.height;
+        let width = area.width;
+        let reverse = config.screen.layout().is_reverse();
 
         let candidate_styles = ItemStyles::new(
             width,
             config.candidate.symbol(),
+            config.candidate.symbol_marked(),
             config.candidate.style().into(),
             config.candidate.style_match().into(),
             config.candidate.style_symbol().into(),
@@ -275,18 +445,48 @@ impl From<&Config> for ListComponent {
         let selection_styles = ItemStyles::new(
             width,
             config.selection.symbol(),
+            config.selection.symbol_marked(),
             config.selection.style().into(),
             config.selection.style_match().into(),
             config.selection.style_symbol().into(),
         );
 
         Self {
-            height,
+            lines,
             offset,
+            reverse,
             candidate_styles,
             selection_styles,
         }
     }
+
+    /// Move the offset of the list so the current selection is always visible.
+    ///
+    /// The offset is the index of the first visible candidate, which is the one closest to the
+    /// gauge: the top line in the default layout and the bottom one in the reverse layout. Since
+    /// the direction is only a rendering concern the same math works for both layouts.
+    pub fn scroll(&mut self, state: &State) {
+        let len = self.lines;
+
+        let selection = state.selection_idx();
+
+        // after a new search the list might be shorter than before, don't leave
+        // visible lines empty if there are enough candidates to fill them
+        let max_offset = state.matches().len().saturating_sub(len);
+        if self.offset > max_offset {
+            self.offset = max_offset;
+        }
+
+        let top_position = self.offset;
+        let last_position = (len + self.offset) - 1;
+
+        // cycle through the list
+        if selection > last_position {
+            self.offset += selection - last_position;
+        } else if selection < top_position {
+            self.offset -= top_position - selection;
+        };
+    }
 }
 
 impl<'r> Render<'r, ListRenderer<'r>> for ListComponent {
@@ -0,0 +1,459 @@
+//! Screen layout: where each part of the UI goes
+//!
+//! The UI is drawn inside a `Frame`. The frame takes all the lines available for the program (the
+//! whole screen in full mode or the given number of lines in inline mode) and reserves room for
+//! the margins, the border and the padding. What is left is the inner area, a `Rect` where the
+//! prompt, the gauge and the list are printed. Every component is clipped to the inner area's
+//! width, so they never overflow the border.
+
+use crate::config::components::{Border, Spacing};
+use crate::config::Config;
+use ansi_term::Style;
+use termion::cursor;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// The inner area needs room for the prompt, the gauge and at least one candidate
+const MIN_INNER_HEIGHT: usize = 3;
+const MIN_INNER_WIDTH: usize = 4;
+
+/// A rectangular area of the screen, relative to the top left corner of the UI
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Debug, Clone)]
+struct BorderSymbols {
+    top_left: &'static str,
+    top_right: &'static str,
+    bottom_left: &'static str,
+    bottom_right: &'static str,
+    horizontal: &'static str,
+    vertical: &'static str,
+}
+
+impl BorderSymbols {
+    fn new(border: &Border) -> Option<Self> {
+        let symbols = match border {
+            Border::None => return None,
+            Border::Rounded => ["╭", "╮", "╰", "╯", "─", "│"],
+            Border::Sharp => ["┌", "┐", "└", "┘", "─", "│"],
+            Border::Double => ["╔", "╗", "╚", "╝", "═", "║"],
+        };
+
+        Some(Self {
+            top_left: symbols[0],
+            top_right: symbols[1],
+            bottom_left: symbols[2],
+            bottom_right: symbols[3],
+            horizontal: symbols[4],
+            vertical: symbols[5],
+        })
+    }
+}
+
+/// Decorations around the UI and the inner area left for the components
+#[derive(Debug, Clone)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    margin: Spacing,
+    padding: Spacing,
+    border: Option<BorderSymbols>,
+    label: Option<String>,
+    style: Style,
+    inner: Rect,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            margin: Spacing::default(),
+            padding: Spacing::default(),
+            border: None,
+            label: None,
+            style: Style::default(),
+            inner: Rect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            },
+        }
+    }
+
+    /// Add margins, padding and a border around the inner area.
+    ///
+    /// If there is not enough room to print the components with all the decorations, they are
+    /// ignored and the whole frame is used as the inner area.
+    pub fn decorate(
+        mut self,
+        margin: Spacing,
+        padding: Spacing,
+        border: &Border,
+        label: Option<String>,
+        style: Style,
+    ) -> Self {
+        let border = BorderSymbols::new(border);
+        let border_size = if border.is_some() { 1 } else { 0 };
+
+        let vertical = margin.vertical() + padding.vertical() + border_size * 2;
+        let horizontal = margin.horizontal() + padding.horizontal() + border_size * 2;
+
+        if self.height < vertical + MIN_INNER_HEIGHT || self.width < horizontal + MIN_INNER_WIDTH {
+            log::trace!("not enough room for the frame decorations, ignoring them");
+            return self;
+        }
+
+        self.inner = Rect {
+            x: margin.left + border_size + padding.left,
+            y: margin.top + border_size + padding.top,
+            width: self.width - horizontal,
+            height: self.height - vertical,
+        };
+        self.margin = margin;
+        self.padding = padding;
+        self.border = border;
+        self.label = label;
+        self.style = style;
+
+        self
+    }
+
+    /// Area where the components are printed
+    pub fn inner(&self) -> Rect {
+        self.inner
+    }
+
+    /// Total number of lines used by the UI
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Decorate all the lines of the inner area, returning every line of the UI from top to
+    /// bottom. Missing inner lines are left empty
+    pub fn rows(&self, content: &[String]) -> Vec<String> {
+        let bottom_margin = self.height - self.margin.bottom;
+
+        (0..self.height)
+            .map(|row| {
+                if row < self.margin.top || row >= bottom_margin {
+                    return String::new();
+                }
+
+                if let Some(border) = &self.border {
+                    if row == self.margin.top {
+                        return self.horizontal_border(border.top_left, border.top_right, true);
+                    }
+                    if row == bottom_margin - 1 {
+                        return self.horizontal_border(
+                            border.bottom_left,
+                            border.bottom_right,
+                            false,
+                        );
+                    }
+                }
+
+                let content = row
+                    .checked_sub(self.inner.y)
+                    .and_then(|idx| content.get(idx))
+                    .filter(|_| row < self.inner.y + self.inner.height)
+                    .map(String::as_str)
+                    .unwrap_or("");
+
+                self.row(content)
+            })
+            .collect()
+    }
+
+    /// Decorate one line of the inner area.
+    ///
+    /// The right side of the border is printed first, moving the cursor to its column, so the
+    /// cursor ends up right after the given content.
+    pub fn row(&self, content: &str) -> String {
+        match &self.border {
+            Some(border) => {
+                let right = self.width - self.margin.right - 1;
+
+                format!(
+                    "{}{}\r{}{}{}{}",
+                    cursor::Right(right as u16),
+                    self.style.paint(border.vertical),
+                    " ".repeat(self.margin.left),
+                    self.style.paint(border.vertical),
+                    " ".repeat(self.padding.left),
+                    content,
+                )
+            }
+            None => format!("{}{}", " ".repeat(self.inner.x), content),
+        }
+    }
+
+    fn horizontal_border(&self, left: &str, right: &str, with_label: bool) -> String {
+        let horizontal = match &self.border {
+            Some(border) => border.horizontal,
+            None => return String::new(),
+        };
+        let length = self.width - self.margin.horizontal() - 2;
+
+        // The label goes after the first horizontal line, surrounded by spaces
+        let label = self
+            .label
+            .as_ref()
+            .filter(|_| with_label)
+            .map(|label| clip(label, length.saturating_sub(3)))
+            .filter(|label| !label.is_empty());
+
+        let line = match label {
+            Some(label) => {
+                let rest = length - text_width(&label) - 3;
+
+                format!(
+                    "{} {} {}{}",
+                    horizontal,
+                    label,
+                    horizontal.repeat(rest),
+                    right,
+                )
+            }
+            None => format!("{}{}", horizontal.repeat(length), right),
+        };
+
+        format!(
+            "{}{}",
+            " ".repeat(self.margin.left),
+            self.style.paint(format!("{}{}", left, line))
+        )
+    }
+}
+
+impl From<&Config> for Frame {
+    fn from(config: &Config) -> Self {
+        let (width, height) = config.screen.size();
+
+        Self::new(width, height).decorate(
+            config.screen.margin(),
+            config.screen.padding(),
+            &config.screen.border(),
+            config.screen.border_label(),
+            config.screen.style_border().into(),
+        )
+    }
+}
+
+/// Areas used by each component inside the frame's inner area
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Areas {
+    pub prompt: Rect,
+    pub gauge: Rect,
+    pub header: Rect,
+    pub list: Rect,
+}
+
+impl Areas {
+    /// Split the inner area into one line for the prompt, one line for the gauge, the given
+    /// number of lines for the header and the rest for the list.
+    ///
+    /// In the default layout the components go from top to bottom in that order. In the reverse
+    /// layout the order is the opposite, with the prompt at the bottom.
+    ///
+    /// The header never takes the last line of the list.
+    pub fn new(inner: Rect, header_height: usize, reverse: bool) -> Self {
+        let header_height = header_height.min(inner.height - MIN_INNER_HEIGHT);
+        let list_height = inner.height - 2 - header_height;
+
+        let area = |offset: usize, height: usize| Rect {
+            x: inner.x,
+            y: inner.y + offset,
+            width: inner.width,
+            height,
+        };
+
+        if reverse {
+            Self {
+                list: area(0, list_height),
+                header: area(list_height, header_height),
+                gauge: area(inner.height - 2, 1),
+                prompt: area(inner.height - 1, 1),
+            }
+        } else {
+            Self {
+                prompt: area(0, 1),
+                gauge: area(1, 1),
+                header: area(2, header_height),
+                list: area(2 + header_height, list_height),
+            }
+        }
+    }
+}
+
+/// Number of columns used to display the given text
+pub fn text_width(text: &str) -> usize {
+    text.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Number of columns used to display one grapheme. Wide characters, like most CJK characters
+/// and emojis, take two columns
+pub fn grapheme_width(grapheme: &str) -> usize {
+    grapheme.width()
+}
+
+/// Keep only the graphemes of the text that fit in the given width
+pub fn clip(text: &str, width: usize) -> String {
+    let mut used = 0;
+
+    text.graphemes(true)
+        .take_while(|grapheme| {
+            used += grapheme_width(grapheme);
+            used <= width
+        })
+        .collect()
+}
+
+/// Move the cursor up the given number of lines.
+///
+/// Most terminals will move the cursor one line up when asked to move zero lines, so in that
+/// case nothing is returned.
+pub fn up(lines: usize) -> String {
+    if lines == 0 {
+        String::new()
+    } else {
+        cursor::Up(lines as u16).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(string: &str) -> String {
+        // remove the cursor movement used to print the right border
+        let string = match string.find('\r') {
+            Some(idx) => &string[idx + 1..],
+            None => string,
+        };
+
+        string.to_string()
+    }
+
+    #[test]
+    fn inner_area_without_decorations_test() {
+        let frame = Frame::new(20, 6);
+
+        assert_eq!(
+            frame.inner(),
+            Rect {
+                x: 0,
+                y: 0,
+                width: 20,
+                height: 6
+            }
+        );
+    }
+
+    #[test]
+    fn inner_area_with_decorations_test() {
+        let frame = Frame::new(20, 10).decorate(
+            Spacing::new(1, 2, 1, 2),
+            Spacing::new(0, 1, 0, 1),
+            &Border::Rounded,
+            None,
+            Style::default(),
+        );
+
+        assert_eq!(
+            frame.inner(),
+            Rect {
+                x: 4,
+                y: 2,
+                width: 12,
+                height: 6
+            }
+        );
+    }
+
+    #[test]
+    fn decorations_are_ignored_without_enough_room_test() {
+        let frame = Frame::new(20, 4).decorate(
+            Spacing::default(),
+            Spacing::default(),
+            &Border::Sharp,
+            None,
+            Style::default(),
+        );
+
+        assert_eq!(frame.inner(), Frame::new(20, 4).inner());
+    }
+
+    #[test]
+    fn areas_test() {
+        let inner = Rect {
+            x: 1,
+            y: 1,
+            width: 10,
+            height: 8,
+        };
+        let line = |y: usize, height: usize| Rect {
+            x: 1,
+            y,
+            width: 10,
+            height,
+        };
+
+        let areas = Areas::new(inner, 2, false);
+        assert_eq!(areas.prompt, line(1, 1));
+        assert_eq!(areas.gauge, line(2, 1));
+        assert_eq!(areas.header, line(3, 2));
+        assert_eq!(areas.list, line(5, 4));
+
+        let areas = Areas::new(inner, 2, true);
+        assert_eq!(areas.list, line(1, 4));
+        assert_eq!(areas.header, line(5, 2));
+        assert_eq!(areas.gauge, line(7, 1));
+        assert_eq!(areas.prompt, line(8, 1));
+
+        // The header can't take all the lines of the list
+        let areas = Areas::new(inner, 10, false);
+        assert_eq!(areas.header, line(3, 5));
+        assert_eq!(areas.list, line(8, 1));
+    }
+
+    #[test]
+    fn rows_with_border_and_label_test() {
+        let frame = Frame::new(12, 5).decorate(
+            Spacing::default(),
+            Spacing::default(),
+            &Border::Sharp,
+            Some("files".into()),
+            Style::default(),
+        );
+        let content = vec!["a".to_string(), "b".to_string()];
+
+        let rows = frame
+            .rows(&content)
+            .iter()
+            .map(|r| plain(r))
+            .collect::<Vec<_>>();
+
+        assert_eq!(rows, vec!["┌─ files ──┐", "│a", "│b", "│", "└──────────┘"]);
+    }
+
+    #[test]
+    fn text_width_test() {
+        assert_eq!(text_width("foo"), 3);
+        assert_eq!(text_width("ñu"), 2);
+        assert_eq!(text_width("e\u{301}"), 1);
+        assert_eq!(text_width("日本"), 4);
+
+        assert_eq!(clip("日本語", 5), "日本");
+        assert_eq!(clip("日本語", 6), "日本語");
+        assert_eq!(clip("a日本", 2), "a");
+        assert_eq!(clip("foo", 0), "");
+    }
+}